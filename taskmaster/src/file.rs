@@ -1,33 +1,63 @@
 use std::env;
 use std::error::Error;
 use std::process;
-use std::path::{PathBuf};
+use std::path::{Path, PathBuf};
+use taskmaster::monitor::parsing::settings::Settings;
+use taskmaster::signal::Signal;
 
-pub fn get_config_from_args() -> PathBuf {
-    let args: Vec<String> = env::args().collect();
-    let mut path: PathBuf = PathBuf::new();
-    match args.len() {
-        2 => path.push(&args[1]),
-        3.. => { eprintln!("Taskmaster: Too many arguments"); process::exit(1);},
-        _ => { eprintln!("Taskmaster: Missing config file name"); process::exit(1);},
-    };
+/// Config file path and the settings overriding its `taskmaster` section, from `taskmaster
+/// [--log-file PATH] [--log-level LEVEL] [--log-format text|json] [--syslog rfc5424|rfc3164|journald]
+/// [--syslog-facility FACILITY] [--syslog-tag TAG] [--syslog-socket PATH]
+/// [--reopen-signal SIGNAL] [--childlogdir PATH] [--pidfile PATH] [--check] CONFIG`,
+/// `--check` validating the config instead of running it
+pub fn get_config_from_args() -> (PathBuf, Settings, bool) {
+    let mut args = env::args().skip(1);
+    let mut path: Option<PathBuf> = None;
+    let mut settings = Settings::default();
+    let mut check = false;
+    while let Some(arg) = args.next() {
+        let result: Result<(), Box<dyn Error>> = match arg.as_str() {
+            "--log-file" | "--log-level" | "--log-format" | "--syslog" | "--syslog-facility" | "--syslog-tag" | "--syslog-socket" | "--reopen-signal" | "--childlogdir" | "--pidfile" => match args.next() {
+                Some(value) if arg == "--log-file" => { settings.log_file = Some(PathBuf::from(value)); Ok(()) },
+                Some(value) if arg == "--log-level" => value.parse().map(|level| settings.log_level = Some(level)),
+                Some(value) if arg == "--log-format" => value.parse().map(|format| settings.log_format = Some(format)),
+                Some(value) if arg == "--syslog" => value.parse().map(|protocol| settings.syslog().protocol = protocol),
+                Some(value) if arg == "--syslog-facility" => value.parse().map(|facility| settings.syslog().facility = facility),
+                Some(value) if arg == "--syslog-tag" => { settings.syslog().tag = value; Ok(()) },
+                Some(value) if arg == "--childlogdir" => { settings.childlogdir = Some(PathBuf::from(value)); Ok(()) },
+                Some(value) if arg == "--pidfile" => { settings.pidfile = Some(PathBuf::from(value)); Ok(()) },
+                Some(value) if arg == "--reopen-signal" => Signal::parse(&value).map(|signal| settings.reopen_signal = Some(signal)),
+                Some(value) => { settings.syslog().socket = PathBuf::from(value); Ok(()) },
+                None => Err(format!("Missing value for {arg}").into()),
+            },
+            "--check" => { check = true; Ok(()) },
+            _ if arg.starts_with("--") => Err(format!("Unknown option {arg}").into()),
+            _ if path.is_some() => Err("Too many arguments".into()),
+            _ => { path = Some(PathBuf::from(arg)); Ok(()) },
+        };
+        if let Err(err) = result {
+            eprintln!("Taskmaster: {err}");
+            process::exit(1);
+        }
+    }
+    let path = path.unwrap_or_else(|| { eprintln!("Taskmaster: Missing config file name"); process::exit(1); });
     
-    if let Err(err) = check_file_with_extension(&path, "conf") {
+    if let Err(err) = check_is_file(&path) {
         eprintln!("Taskmaster: Config path: {err}");
         process::exit(1);
     }
 
-    path
+    (path, settings, check)
 }
 
-pub fn check_valid_path(path: &PathBuf) -> Result<(), Box<dyn Error>> {
+pub fn check_valid_path(path: &Path) -> Result<(), Box<dyn Error>> {
     match path.try_exists() {
         Ok(true) => Ok(()),
         _ => Err("Path does not point to an existing entity".into()),
     }
 }
 
-pub fn check_is_file(path: &PathBuf) -> Result<(), Box<dyn Error>> {
+pub fn check_is_file(path: &Path) -> Result<(), Box<dyn Error>> {
     check_valid_path(path)?;
     if !path.is_file() {
         Err("File does not exist or is not accessible".into())
@@ -35,16 +65,3 @@ pub fn check_is_file(path: &PathBuf) -> Result<(), Box<dyn Error>> {
         Ok(())
     }
 }
-
-pub fn check_file_with_extension(path: &PathBuf, extension: &str) -> Result<(), Box<dyn Error>> {
-    check_is_file(path)?;
-    match path.extension() {
-        Some(x) => if x != extension {
-            Err("Wrong file extension")
-        } else {
-            Ok(())
-        }
-        None => Err("Failed to retreive file extension"),
-    }?;
-    Ok(())
-}
\ No newline at end of file
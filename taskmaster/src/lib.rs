@@ -4,6 +4,7 @@ mod sys;
 
 use monitor::*;
 use monitor::instruction::*;
+use monitor::parsing::settings::Settings;
 use std::io::{self};
 use std::sync::mpsc::{self, Sender};
 use std::{thread};
@@ -12,19 +13,21 @@ use std::path::PathBuf;
 
 pub struct Taskmaster {
     config_file_path: PathBuf,
+    settings: Settings,
 }
 
 impl Taskmaster {
-    pub fn new(file_path: PathBuf) -> Result<Self, Box<dyn Error>> {
+    pub fn new(file_path: PathBuf, settings: Settings) -> Result<Self, Box<dyn Error>> {
         Ok(Taskmaster {
             config_file_path: file_path,
+            settings,
         })
     }
 
     pub fn execute(mut self) -> Result<(), Box<dyn Error>> {
         let (sender, receiver) = mpsc::channel::<Instruction>();
         let sender_clone = sender.clone();
-        let mut monitor = Monitor::new(&self.config_file_path)?;
+        let mut monitor = Monitor::new(&self.config_file_path, &self.settings)?;
         thread::spawn(move || {
             monitor.execute(receiver, sender_clone);
         });
@@ -37,6 +40,10 @@ impl Taskmaster {
         loop {
             buff.clear();
             io::stdin().read_line(&mut buff).expect("Failed to read");
+            if buff.trim().is_empty() {
+                sender.send(Instruction::StopFollowing).ok();
+                continue;
+            }
             let instruction: Instruction = match buff.parse() {
                 Ok(res) => res,
                 Err(err) => {
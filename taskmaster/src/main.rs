@@ -1,10 +1,22 @@
 use std::process;
 mod file;
 use taskmaster::Taskmaster;
+use taskmaster::monitor::parsing::check;
 
 fn main() {
 
-    let taskmaster = Taskmaster::new(file::get_config_from_args()).unwrap_or_else(|err| {
+    let (config_file_path, settings, check) = file::get_config_from_args();
+    if check {
+        let problems = check::check(&config_file_path, &settings);
+        problems.iter().for_each(|problem| eprintln!("{problem}"));
+        if !problems.is_empty() {
+            eprintln!("Taskmaster: {} problem{} found in {}", problems.len(), if problems.len() == 1 { "" } else { "s" }, config_file_path.display());
+            process::exit(1);
+        }
+        println!("Taskmaster: {} is valid", config_file_path.display());
+        return;
+    }
+    let taskmaster = Taskmaster::new(config_file_path, settings).unwrap_or_else(|err| {
         eprintln!("Taskmaster: {err}");
         process::exit(1);
     });
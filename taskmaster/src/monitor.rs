@@ -1,74 +1,128 @@
 pub mod processus;
 pub mod program;
 pub mod logger;
+pub mod output;
 pub mod instruction;
 pub mod parsing;
 
 use std::error::Error;
+use std::{fs, io};
+use std::io::{BufRead, BufReader};
 use std::collections::{HashMap, VecDeque};
 use std::sync::atomic::Ordering;
 use std::sync::mpsc::{Sender, Receiver};
 use std::{thread, vec, process};
-use std::time::Duration;
-use std::path::PathBuf;
+use std::time::{Duration, Instant, SystemTime};
+use std::path::{Path, PathBuf};
 use std::process::ExitStatus;
 use std::os::unix::process::ExitStatusExt;
 use processus::{Status, Processus};
-use logger::Logger;
+use output::{Stream, sink};
+use logger::{Logger, Record};
 use program::Program;
-use parsing::Parsing;
+use parsing::{Parsed, Parsing, glob};
+use parsing::settings::Settings;
+use parsing::sink::{SinkConfig, SinkName};
 use instruction::Instruction;
+use output::LineFormat;
+use regex::Regex;
 
 use crate::signal::{Signal};
 use crate::sys::{Libc, self};
+use crate::sys::inotify::{self, Inotify};
 
 use self::processus::id::Id;
 
 const INACTIVE_FLAG: &str = "Inactive";
+// Lines of recent output written with an unexpected exit
+const LOG_OUTPUT_LINES: usize = 20;
+// Time given to the capture threads to read the last output of an exited processus
+const OUTPUT_DRAIN_TIMEOUT: Duration = Duration::from_millis(200);
+// Time without change of the config files before an automatic reload, editors write in several steps
+const AUTO_RELOAD_DELAY: Duration = Duration::from_millis(500);
 
 fn sig_handler(_: i32) {
     sys::RELOAD_INSTRUCTION.store(true, Ordering::SeqCst);
 }
 
+fn reopen_handler(_: i32) {
+    sys::REOPEN_INSTRUCTION.store(true, Ordering::SeqCst);
+}
+
+// File searched by `grep`, with the instance and stream writing it
+struct GrepFile {
+    name: String,
+    num: usize,
+    stream: Stream,
+    format: LineFormat,
+    path: PathBuf,
+}
+
 pub struct Monitor {
     config_file_path: PathBuf,
     processus: Vec<Processus>,
     logger: Logger,
+    /// Settings of the command line, applied again over the config file on reload
+    overrides: Settings,
+    settings: Settings,
     programs: HashMap<String, Program>,
+    /// Watcher of the directories of the config files and of the include globs, when `auto_reload` is enabled
+    watcher: Option<Inotify>,
+    /// Config files read and include globs, whose files are reloaded when they change
+    watched: (Vec<PathBuf>, Vec<PathBuf>),
+    /// Last change of the config files not reloaded yet
+    config_changed: Option<Instant>,
 }
 
 impl Monitor {
-    pub fn new(file_path: &PathBuf) -> Result<Self, Box<dyn Error>> {
-        let mut programs = Parsing::parse(file_path)?;
-        let logger = Logger::new("taskmaster.log")?;
+    pub fn new(file_path: &PathBuf, overrides: &Settings) -> Result<Self, Box<dyn Error>> {
+        let Parsed { settings, mut programs, files, includes, .. } = Parsing::parse(file_path, overrides)?;
+        let mut logger = Logger::new(&settings.logger())?;
+        let childlogdir = settings.childlogdir();
+        fs::create_dir_all(&childlogdir).map_err(|err| format!("Failed to create the childlogdir '{}': {err}", childlogdir.display()))?;
+        if let Some(pidfile) = &settings.pidfile {
+            fs::write(pidfile, format!("{}\n", process::id()))
+                .map_err(|err| format!("Failed to write the pidfile '{}': {err}", pidfile.display()))?;
+        }
         let mut processus: Vec<Processus> = Vec::new();
 
         let mut invalid_confs = Vec::<String>::new();
         for (name, program) in programs.iter_mut() {
             if let Err(err) = program.build_command() {
-                eprintln!("Program {name}: {err}");
+                logger.log(Record::error(format!("Invalid configuration: {err}")).program(name));
                 invalid_confs.push(name.to_owned());
                 continue;
             }
-            for _ in 0..program.config.numprocs {
-                processus.push(Processus::new(name, program));
+            for num in 0..program.config.numprocs {
+                processus.push(Processus::new(name, num, program));
             }
         }
         for name in &invalid_confs {
             programs.remove(name);
         }
         
-        Ok(Monitor {
+        let mut monitor = Monitor {
             config_file_path: file_path.to_owned(),
             processus,
             logger,
+            overrides: overrides.to_owned(),
+            settings,
             programs,
-        })
+            watcher: None,
+            watched: (Vec::new(), Vec::new()),
+            config_changed: None,
+        };
+        monitor.watch(files, includes);
+        Ok(monitor)
     }
 
     pub fn execute(&mut self, receiver: Receiver<Instruction>, mut sender: Sender<Instruction>) {
         if Libc::signal(Signal::SIGHUP, sig_handler).is_err() {
-            eprintln!("Signal function failed, taskmaster won't be able to handle SIGHUP");
+            self.logger.log(Record::warn("Signal function failed, taskmaster won't be able to handle SIGHUP"));
+        }
+        let reopen_signal = self.settings.logger().reopen_signal;
+        if Libc::signal(reopen_signal, reopen_handler).is_err() {
+            self.logger.log(Record::warn(format!("Signal function failed, taskmaster won't be able to handle {reopen_signal:?}")));
         }
         self.autostart();
 
@@ -79,6 +133,19 @@ impl Monitor {
                 instruction_queue.push_front(Instruction::Reload);
                 sys::RELOAD_INSTRUCTION.store(false, Ordering::SeqCst);
             }
+            if sys::REOPEN_INSTRUCTION.swap(false, Ordering::SeqCst) {
+                self.reopen_logs();
+            }
+            let (files, includes) = &self.watched;
+            let watched = |path: &Path| files.iter().any(|file| file == path) || includes.iter().any(|include| glob::is_match(include, path));
+            if self.watcher.as_mut().is_some_and(|watcher| watcher.changed(watched)) {
+                self.config_changed = Some(Instant::now());
+            }
+            if self.config_changed.is_some_and(|changed| changed.elapsed() >= AUTO_RELOAD_DELAY) {
+                self.config_changed = None;
+                self.logger.log(Record::info("Config file changed"));
+                instruction_queue.push_back(Instruction::Reload);
+            }
             if let Ok(instruction) = receiver.try_recv() {
                 instruction_queue.push_back(instruction);
             }
@@ -86,10 +153,17 @@ impl Monitor {
                 match instruction {
                     // Instruction from cli
                     Instruction::Status => self.status_command(),
+                    Instruction::Details(programs) => self.details_command(programs),
+                    Instruction::Env(programs) => self.env_command(programs),
                     Instruction::Start(programs) => self.start_command(programs),
                     Instruction::Stop(programs) => self.stop_command(programs),
                     Instruction::Restart(programs) => self.restart_command(programs, &mut sender),
                     Instruction::Reload => self.reload(),
+                    Instruction::Send(name, num, text) => self.send_command(name, num, text),
+                    Instruction::Tail(name, num, stream, lines, follow) => self.tail_command(name, num, stream, lines, follow),
+                    Instruction::StopFollowing => self.stop_following(),
+                    Instruction::LastLog(name, num) => self.lastlog_command(name, num),
+                    Instruction::Grep(name, num, pattern, since, stream) => self.grep_command(name, num, pattern, since, stream, &mut sender),
                     // Instruction not from Cli
                     Instruction::RemoveProcessus(id) => self.remove_processus(id),
                     Instruction::StartProcessus(id) => self.start_processus(id, false),
@@ -97,13 +171,15 @@ impl Monitor {
                     Instruction::RetryStartProcessus(id) => self.start_processus(id, true),
                     Instruction::SetStatus(id, status) => self.set_status(id, status),
                     Instruction::KillProcessus(id) => self.kill_processus(id),
+                    Instruction::Log(record) => self.logger.log(record),
                     Instruction::Exit => self.stop_all(),
                 }
             }
+            self.report_suppressed();
             let mut iteration_instructions: VecDeque<Instruction> = VecDeque::new();
             iteration_instructions.extend(self.monitor());
             instruction_queue.append(&mut iteration_instructions);
-            thread::sleep(Duration::from_millis(300));
+            thread::sleep(self.settings.poll_interval());
         }
     }
 }
@@ -125,31 +201,40 @@ impl Monitor {
             if processus.status != Status::Reloading {
                 processus.status = Status::Inactive;
             }
-            self.logger.log(&format!("Sigkill processus {} {}", processus.name, processus.id));
+            self.logger.log(Record::warn("Sigkill processus").processus(processus));
         }
     }
 
     fn set_status(&mut self, id: Id, status: Status) {
         if let Some(processus) = Self::get_processus(&mut self.processus, id) {
             processus.status = status;
-            self.logger.log(&format!("Seting status of processus {} {} to Active", processus.name, processus.id));
+            self.logger.log(Record::info("Seting status of processus").processus(processus));
         }
     }
 
     fn start_processus(&mut self, id: Id, restart: bool) {
         if let Some(processus) = Self::get_processus(&mut self.processus, id) {
             if let Some(program) = self.programs.get_mut(&processus.name) {
-                if let Some(command) = &mut program.command {
-                    match processus.start_child(command, program.config.startretries, program.config.umask, restart) {
-                        Ok(false) => {self.logger.log(&format!("Starting processus {} {}, {} atempt left", processus.name, processus.id, processus.retries));},
-                        Ok(true) => {self.logger.log(&format!("Failed to start processus {} {}, no atempt left", processus.name, processus.id));},
-                        Err(err) => {eprintln!("{err:?}");self.logger.log(&format!("{err:?}"));},
-                    } 
+                if let Some(instance) = program.instances.get_mut(processus.num) {
+                    match processus.start_child(instance, program.config.startretries, program.config.umask, restart) {
+                        Ok(false) => {
+                            // Only fails without file descriptors left, the program sinks then skip this instance
+                            if let Some(stdin) = processus.child.as_ref().and_then(|child| child.stdin.as_ref()) {
+                                sink::register_input(&processus.name, processus.num, stdin).ok();
+                            }
+                            self.logger.log(Record::info(format!("Starting processus, {} atempt left", processus.retries)).processus(processus));
+                        },
+                        Ok(true) => {
+                            let output = program.recent_output(processus.num, Some(LOG_OUTPUT_LINES), None);
+                            self.logger.log(Record::error("Failed to start processus, no atempt left").processus(processus).output(output));
+                        },
+                        Err(err) => self.logger.log(Record::error(format!("Failed to start processus: {err}")).processus(processus)),
+                    }
                 } else {
-                    eprintln!("Can't find command to start processus {} {}", processus.name, processus.id);
+                    self.logger.log(Record::error("Can't find command to start processus").processus(processus));
                 }
             } else {
-                eprintln!("Can't find program to start processus {} {}", processus.name, processus.id);
+                self.logger.log(Record::error("Can't find program to start processus").processus(processus));
             }
         }
     }
@@ -157,7 +242,7 @@ impl Monitor {
     fn reset_processus(&mut self, id: Id) {
         if let Some(processus) = Self::get_processus(&mut self.processus, id) {
             if let Some(program) = self.programs.get(&processus.name) {
-                self.logger.log(&format!("Reset processus {} {}", processus.name, processus.id));
+                self.logger.log(Record::info("Reset processus").processus(processus));
                 processus.reset_child(program.config.startretries)
             }
         }
@@ -178,8 +263,8 @@ impl Monitor {
                     program.activate();
                     self.programs.insert(processus_name.to_owned(), program);
                     let program = self.programs.get(&processus_name).unwrap();
-                    for _ in 0..program.config.numprocs {
-                        self.processus.push(Processus::new(&processus_name, program));
+                    for num in 0..program.config.numprocs {
+                        self.processus.push(Processus::new(&processus_name, num, program));
                     }
                     if program.config.autostart {
                         self.start_command(vec![processus_name]);
@@ -271,6 +356,15 @@ impl Monitor {
         }
     }
 
+    /// Exits while starting, or while running with a code that isn't in `exitcodes`
+    fn is_unexpected_exit(program: &Program, processus: &Processus, code: ExitStatus) -> bool {
+        match processus.status {
+            Status::Starting => true,
+            Status::Active => !code.code().is_some_and(|code| program.config.exitcodes.contains(&code)),
+            _ => false,
+        }
+    }
+
     fn monitor(&mut self) -> Vec<Instruction> {
         let mut instructions = Vec::new();
 
@@ -280,12 +374,27 @@ impl Monitor {
                     Err(_) => panic!("Try_wait failed on processus {} {}", processus.id, processus.name),
                     Ok(code) => {
                         if let Some(code) = code {
-                            if let Some(signal) = code.signal() {
-                                if processus.status != Status::Reloading {
-                                    self.logger.log(&format!("Processus {} {} was stopped by a signal: {}", processus.name, processus.id, signal));
-                                    instructions.push(Instruction::ResetProcessus(processus.id));
-                                    continue;
+                            let program = self.programs.get(&processus.name).unwrap();
+                            let unexpected = Self::is_unexpected_exit(program, processus, code);
+                            // The exit is logged with the last lines of the output, handled on a later tick until they are read
+                            if unexpected && !processus.output_drained(OUTPUT_DRAIN_TIMEOUT) {
+                                continue;
+                            }
+                            sink::unregister_input(&processus.name, processus.num);
+                            let record = if unexpected {
+                                let output = program.recent_output(processus.num, Some(LOG_OUTPUT_LINES), None);
+                                if processus.status == Status::Starting && processus.retries == 0 {
+                                    Record::error("Processus exited while starting, no atempt left").output(output)
+                                } else {
+                                    Record::warn("Processus exited unexpectedly").output(output)
                                 }
+                            } else {
+                                Record::info("Processus exited")
+                            };
+                            self.logger.log(record.processus(processus).exit_status(code));
+                            if code.signal().is_some() && processus.status != Status::Reloading {
+                                instructions.push(Instruction::ResetProcessus(processus.id));
+                                continue;
                             }
                         }
                         if let Some(instruction) = Self::monitor_processus(self.programs.get(&processus.name).unwrap(), processus, code) {
@@ -301,23 +410,104 @@ impl Monitor {
     }
 
     fn status_command(&mut self) {
-        println!("{:-<55}", "-");
-        println!("| {:^5} | {:^20} | {:^20} |", "ID", "NAME", "STATUS");
-        println!("{:-<55}", "-");
+        println!("{:-<79}", "-");
+        println!("| {:^5} | {:^20} | {:^20} | {:^9} | {:^9} |", "ID", "NAME", "STATUS", "DROPPED", "TRUNCATED");
+        println!("{:-<79}", "-");
         for proc in self.processus.iter_mut() {
-                println!("| {:^5} | {:^20} | {:^20} |", proc.id, proc.name.chars().take(20).collect::<String>(), proc.status);
+                let (dropped, truncated) = self.programs.get(&proc.name).map_or((0, 0), |program| program.suppressed(proc.num));
+                println!("| {:^5} | {:^20} | {:^20} | {:^9} | {:^9} |", proc.id, proc.name.chars().take(20).collect::<String>(), proc.status, dropped, truncated);
+        }
+        println!("{:-<79}", "-");
+        for proc in self.processus.iter() {
+            let program = match self.programs.get(&proc.name) {
+                Some(program) => program,
+                None => continue,
+            };
+            for output in [Stream::Stdout, Stream::Stderr].into_iter().filter_map(|stream| program.output(proc.num, stream)) {
+                for file in output.files() {
+                    let file = file.lock().unwrap_or_else(|err| err.into_inner());
+                    println!("{}:{} {}: {}", proc.name, proc.num, output.stream, file.path().display());
+                }
+            }
+        }
+        self.logger.log(Record::debug("Displaying Status"));
+    }
+
+    fn details_command(&mut self, names: Vec<String>) {
+        for name in names {
+            let program = if let Some(program) = self.programs.get(&name) {
+                program
+            } else {
+                self.logger.log(Record::warn("Program not found").program(&name));
+                continue;
+            };
+            let config = &program.config;
+            println!("{name}:");
+            println!("    cmd: {}", config.cmd);
+            println!("    shell: {}", config.shell);
+            if let Some(group) = &config.group {
+                println!("    group: {group}");
+            }
+            println!("    workingdir: {}", config.workingdir.display());
+            println!("    numprocs: {}", config.numprocs);
+            println!("    umask: {:03o}", config.umask);
+            println!("    autostart: {}", config.autostart);
+            println!("    autorestart: {}", config.autorestart);
+            println!("    stdout: {}", config.stdout);
+            if config.redirect_stderr {
+                println!("    stderr: redirected to stdout");
+            } else {
+                println!("    stderr: {}", config.stderr);
+            }
+            println!("    clear_env: {}", config.clear_env);
+            if let Ok(scheduling) = config.scheduling() {
+                println!("    scheduling: {scheduling}");
+            }
+            for processus in self.processus.iter().filter(|e| e.name == name) {
+                println!("    processus {} (instance {}): {}", processus.id, processus.num, processus.status.to_string().trim());
+                if let Some(instance) = program.instances.get(processus.num) {
+                    println!("        cmd: {}", instance.config.cmd);
+                    for output in [&instance.stdout, &instance.stderr].into_iter().flatten() {
+                        for file in output.files() {
+                            let file = file.lock().unwrap_or_else(|err| err.into_inner());
+                            println!("        {}: {}", output.stream, file.path().display());
+                        }
+                    }
+                    match &instance.landlock {
+                        Some(landlock) => println!("        landlock: {landlock}"),
+                        None => println!("        landlock: none"),
+                    }
+                }
+            }
+            self.logger.log(Record::debug("Displaying details").program(&name));
+        }
+    }
+
+    fn env_command(&mut self, names: Vec<String>) {
+        for name in names {
+            let program = if let Some(program) = self.programs.get(&name) {
+                program
+            } else {
+                self.logger.log(Record::warn("Program not found").program(&name));
+                continue;
+            };
+            for (num, instance) in program.instances.iter().enumerate() {
+                println!("{name} (instance {num}):");
+                for (key, value) in &instance.environment {
+                    println!("    {key}={value}");
+                }
+            }
+            self.logger.log(Record::debug("Displaying environment").program(&name));
         }
-        println!("{:-<55}", "-");
-        self.logger.log("Displaying Status");
     }
 
     fn start_command(&mut self, names: Vec<String>) {
         for name in names {
-            self.logger.log(&format!("Starting program {}", &name));
             if self.programs.get_mut(&name).is_none() {
-                eprintln!("Program not found: {name}");
+                self.logger.log(Record::warn("Program not found").program(&name));
                 continue;
             }
+            self.logger.log(Record::info("Starting program").program(&name));
             let filtered_processus_ids: Vec<Id> = self.processus.iter().filter_map(|e| {
                 if e.name == name && e.status == Status::Inactive {
                     Some(e.id)
@@ -331,22 +521,242 @@ impl Monitor {
         }
     }
 
+    fn send_command(&mut self, name: String, num: Option<usize>, text: String) {
+        if !self.programs.contains_key(&name) {
+            self.logger.log(Record::warn("Program not found").program(&name));
+            return;
+        }
+        for processus in self.processus.iter_mut().filter(|e| e.name == name && num.is_none_or(|num| e.num == num)) {
+            match processus.send(&text) {
+                Ok(()) => self.logger.log(Record::debug("Sent input to processus").processus(processus)),
+                Err(err) => self.logger.log(Record::warn(format!("Failed to send input: {err}")).processus(processus)),
+            }
+        }
+    }
+
+    fn tail_command(&mut self, name: String, num: Option<usize>, stream: Stream, lines: usize, follow: bool) {
+        let program = if let Some(program) = self.programs.get(&name) {
+            program
+        } else {
+            self.logger.log(Record::warn("Program not found").program(&name));
+            return;
+        };
+        let nums: Vec<usize> = match num {
+            Some(num) if num >= program.instances.len() => {
+                self.logger.log(Record::warn(format!("Instance {num} not found")).program(&name));
+                return;
+            },
+            Some(num) => vec![num],
+            None => (0..program.instances.len()).collect(),
+        };
+        let memory = program.config.sinks(stream).contains(&SinkConfig::Name(SinkName::Memory));
+        let mut paths = Vec::new();
+        for num in nums {
+            let output = match program.output(num, stream) {
+                Some(output) => output,
+                None => {
+                    self.logger.log(Record::warn(format!("{stream} of instance {num} is not captured")).program(&name));
+                    continue;
+                },
+            };
+            // Without a file sink the lines kept in memory are printed
+            let files: Vec<_> = output.files().collect();
+            if files.is_empty() {
+                match memory {
+                    true => program.recent_output(num, Some(lines), Some(stream)).iter().for_each(|line| println!("{line}")),
+                    false => self.logger.log(Record::warn(format!("{stream} of instance {num} isn't kept in a file or in memory")).program(&name)),
+                }
+            }
+            for file in files {
+                let file = file.lock().unwrap_or_else(|err| err.into_inner());
+                // Instances sharing a file are only printed once
+                if !paths.contains(&file.path().to_owned()) {
+                    paths.push(file.path().to_owned());
+                    match file.tail(lines) {
+                        Ok(tail) => tail.iter().for_each(|line| println!("{line}")),
+                        Err(err) => self.logger.log(Record::warn(format!("Failed to read '{}': {err}", file.path().display())).program(&name)),
+                    }
+                }
+            }
+            if follow {
+                output.follow.store(true, Ordering::SeqCst);
+            }
+        }
+        if follow {
+            println!("Following {name} {stream}, press Enter to stop");
+        }
+        self.logger.log(Record::debug(format!("Displaying {stream}")).program(&name));
+    }
+
+    fn lastlog_command(&mut self, name: String, num: Option<usize>) {
+        let program = if let Some(program) = self.programs.get(&name) {
+            program
+        } else {
+            self.logger.log(Record::warn("Program not found").program(&name));
+            return;
+        };
+        for num in (0..program.instances.len()).filter(|&e| num.is_none_or(|num| e == num)) {
+            println!("{name}:{num}:");
+            let lines = program.recent_output(num, None, None);
+            if lines.is_empty() {
+                println!("    no recent output");
+            }
+            lines.iter().for_each(|line| println!("    {line}"));
+        }
+        self.logger.log(Record::debug("Displaying recent output").program(&name));
+    }
+
+    /// Searches the files of a program, of the programs of a group or of `all` of them
+    fn grep_command(&mut self, name: String, num: Option<usize>, pattern: Regex, since: Option<Duration>, stream: Option<Stream>, sender: &mut Sender<Instruction>) {
+        let mut names: Vec<&String> = match self.programs.get_key_value(&name) {
+            Some((name, _)) => vec![name],
+            None if name == "all" => self.programs.keys().collect(),
+            None => self.programs.iter().filter(|(_, program)| program.config.group.as_ref() == Some(&name)).map(|(name, _)| name).collect(),
+        };
+        if names.is_empty() {
+            self.logger.log(Record::warn("Program or group not found").program(&name));
+            return;
+        }
+        names.sort();
+        let since = match since.map(|since| SystemTime::now().checked_sub(since)) {
+            Some(None) => {
+                self.logger.log(Record::warn("The --since duration goes back before 1970").program(&name));
+                return;
+            },
+            since => since.flatten(),
+        };
+        let mut paths = Vec::new();
+        let mut files = Vec::new();
+        for name in names {
+            let program = &self.programs[name];
+            for num in (0..program.instances.len()).filter(|&e| num.is_none_or(|num| e == num)) {
+                let streams = [Stream::Stdout, Stream::Stderr].into_iter().filter(|&e| stream.is_none_or(|stream| e == stream));
+                for output in streams.filter_map(|stream| program.output(num, stream)) {
+                    for file in output.files() {
+                        let history = file.lock().unwrap_or_else(|err| err.into_inner()).history();
+                        for path in history {
+                            // Instances sharing a file are only searched once
+                            if paths.contains(&path) {
+                                continue;
+                            }
+                            paths.push(path.to_owned());
+                            files.push(GrepFile {
+                                name: name.to_owned(),
+                                num,
+                                stream: output.stream,
+                                format: output.format.clone(),
+                                path,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        self.logger.log(Record::debug(format!("Searching the output for '{pattern}' in {} files", files.len())).program(&name));
+        // The files can be large, they are read without holding the monitor
+        let sender = sender.clone();
+        thread::spawn(move || Self::grep_files(files, &pattern, since, sender));
+    }
+
+    /// Prints the matching lines, the files that can't be read are logged by the monitor
+    fn grep_files(files: Vec<GrepFile>, pattern: &Regex, since: Option<SystemTime>, sender: Sender<Instruction>) {
+        let since_timestamp = since.and_then(|since| since.duration_since(SystemTime::UNIX_EPOCH).ok()).map(|since| since.as_secs() as i64);
+        let mut matches = Vec::new();
+        for file in files {
+            let modified = fs::metadata(&file.path).and_then(|metadata| metadata.modified());
+            if since.is_some_and(|since| modified.is_ok_and(|modified| modified < since)) {
+                continue;
+            }
+            let mut reader = match fs::File::open(&file.path) {
+                Ok(handle) => BufReader::new(handle),
+                Err(err) if err.kind() == io::ErrorKind::NotFound => continue,
+                Err(err) => {
+                    let record = Record::warn(format!("Failed to read '{}': {err}", file.path.display())).program(&file.name);
+                    sender.send(Instruction::Log(record)).ok();
+                    continue;
+                },
+            };
+            let mut buffer = Vec::new();
+            loop {
+                buffer.clear();
+                match reader.read_until(b'\n', &mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => (),
+                }
+                let line = String::from_utf8_lossy(&buffer);
+                let line = line.trim_end_matches(['\n', '\r']);
+                let parsed = file.format.parse(line).unwrap_or_default();
+                if parsed.time.zip(since_timestamp).is_some_and(|(time, since)| time < since) {
+                    continue;
+                }
+                let text = parsed.line.unwrap_or(line);
+                if pattern.is_match(text) {
+                    let label = format!("{}:{} [{}] {}", file.name, parsed.instance.unwrap_or(file.num), file.stream, parsed.time_text.unwrap_or("-"));
+                    matches.push((parsed.time, format!("{label}: {text}")));
+                }
+            }
+        }
+        // Lines of the different files are merged in the order they were written
+        matches.sort_by_key(|(time, _)| *time);
+        matches.iter().for_each(|(_, line)| println!("{line}"));
+    }
+
+    fn reopen_logs(&mut self) {
+        if let Err(err) = self.logger.reopen() {
+            self.logger.log(Record::error(format!("Failed to reopen the log: {err}")));
+        }
+        for (name, program) in &self.programs {
+            for instance in &program.instances {
+                for output in [&instance.stdout, &instance.stderr].into_iter().flatten() {
+                    for file in output.files() {
+                        let mut file = file.lock().unwrap_or_else(|err| err.into_inner());
+                        if let Err(err) = file.reopen() {
+                            self.logger.log(Record::error(format!("Failed to reopen '{}': {err}", file.path().display())).program(name));
+                        }
+                    }
+                }
+            }
+        }
+        self.logger.log(Record::info("Reopened the log files"));
+    }
+
+    // Programs that went quiet after a burst still get their "suppressed N lines" marker
+    fn report_suppressed(&self) {
+        for program in self.programs.values() {
+            for instance in &program.instances {
+                for output in [&instance.stdout, &instance.stderr].into_iter().flatten() {
+                    output.report_suppressed();
+                }
+            }
+        }
+    }
+
+    fn stop_following(&mut self) {
+        for program in self.programs.values() {
+            for instance in &program.instances {
+                for output in [&instance.stdout, &instance.stderr].into_iter().flatten() {
+                    output.follow.store(false, Ordering::SeqCst);
+                }
+            }
+        }
+    }
+
     fn stop_command(&mut self, names: Vec<String>) {
         for name in names {
             let program = if let Some(program) = self.programs.get_mut(&name) {
                 program
             } else {
-                eprintln!("Program not found: {name}");
+                self.logger.log(Record::warn("Program not found").program(&name));
                 continue;
             };
             for processus in self.processus.iter_mut().filter(|e| e.name == name) {
-                Self::stop_processus(processus, program);
+                Self::stop_processus(&mut self.logger, processus, program);
             }
-            self.logger.log(&format!("Stoping {}", &name));
+            self.logger.log(Record::info("Stoping program").program(&name));
         }
     }
 
-    fn stop_processus(processus: &mut Processus, program: &mut Program) {
+    fn stop_processus(logger: &mut Logger, processus: &mut Processus, program: &mut Program) {
         if let Some(child) = processus.child.as_mut() {
             match child.try_wait() {
                 Ok(Some(exitstatus)) => {
@@ -354,7 +764,7 @@ impl Monitor {
                 },
                 Ok(None) => {
                     if let Err(err) = processus.stop_child(program.config.stopsignal, program.config.startretries) {
-                        eprintln!("{err}");
+                        logger.log(Record::error(format!("Failed to stop processus: {err}")).processus(processus));
                     }
                 }
                 Err(_) => {
@@ -366,8 +776,8 @@ impl Monitor {
 
     fn restart_command(&mut self, names: Vec<String>, sender: &mut Sender<Instruction>) {
         for name in &names {
-            if self.programs.get(name).is_none() {
-                eprintln!("Program not found: {name}");
+            if !self.programs.contains_key(name) {
+                self.logger.log(Record::warn("Program not found").program(name));
                 return ;
             }
         }
@@ -375,7 +785,7 @@ impl Monitor {
         self.stop_command(names.to_owned());
         
         for name in names {
-            self.logger.log(&format!("Restarting {name}"));
+            self.logger.log(Record::info("Restarting program").program(&name));
             let duration = Duration::new(self.programs.get(&name).expect("program not found").config.stoptime as u64, 0);
             let sender = sender.clone();
             thread::spawn(move || {
@@ -389,7 +799,7 @@ impl Monitor {
         let mut to_start: Vec<String> = Vec::new();
         for (name, program) in self.programs.iter() {
             if program.config.autostart {
-                self.logger.log(&format!("Autostart {name}"));
+                self.logger.log(Record::info("Autostart").program(name));
                 to_start.push(name.to_owned());
             }
         }
@@ -398,7 +808,7 @@ impl Monitor {
 
     fn stop_all(&mut self) {
         let mut to_stop = Vec::new();
-        self.logger.log("Shutting down taskmaster");
+        self.logger.log(Record::info("Shutting down taskmaster"));
         for (name, _) in self.programs.iter() {
             to_stop.push(name.to_owned());
         }
@@ -412,15 +822,87 @@ impl Monitor {
                 }
             }
         }
+        if let Some(pidfile) = &self.settings.pidfile {
+            fs::remove_file(pidfile).ok();
+        }
         process::exit(0);
     }
     
+    fn apply_scheduling(&self, name: &str, program: &Program) -> Result<(), Box<dyn Error>> {
+        let scheduling = program.config.scheduling()?;
+        let old = self.programs.get(name).ok_or("program is not loaded")?.config.scheduling()?;
+        for processus in self.processus.iter().filter(|e| e.name == name) {
+            if let Some(child) = &processus.child {
+                scheduling.apply(child.id(), &old).map_err(|err| format!("processus {}: {err}", processus.id))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The log settings and `poll_interval` apply on reload, `pidfile` and `reopen_signal` on restart
+    fn apply_settings(&mut self, mut settings: Settings) {
+        let (old, new) = (self.settings.logger(), settings.logger());
+        if (&old.path, old.level, old.format, &old.syslog) != (&new.path, new.level, new.format, &new.syslog) {
+            match Logger::new(&new) {
+                Ok(logger) => {
+                    self.logger = logger;
+                    self.logger.log(Record::info("Applied the new log settings"));
+                },
+                Err(err) => {
+                    self.logger.log(Record::error(format!("Failed to apply the new log settings: {err}")));
+                    settings.log_file = self.settings.log_file.take();
+                    settings.log_level = self.settings.log_level;
+                    settings.log_format = self.settings.log_format;
+                    settings.syslog = self.settings.syslog.take();
+                },
+            }
+        }
+        if settings.pidfile != self.settings.pidfile || new.reopen_signal != old.reopen_signal {
+            self.logger.log(Record::warn("Changes of pidfile and reopen_signal apply when taskmaster restarts"));
+            settings.pidfile = self.settings.pidfile.take();
+            settings.reopen_signal = self.settings.reopen_signal;
+        }
+        self.settings = settings;
+    }
+
+    /// Watches the config `files` and the files matching the `includes` globs, even the ones not
+    /// created yet, when `auto_reload` is enabled, replacing the previous watcher
+    fn watch(&mut self, files: Vec<PathBuf>, includes: Vec<PathBuf>) {
+        self.watcher = None;
+        if self.settings.auto_reload != Some(true) {
+            return;
+        }
+        if !inotify::SUPPORTED {
+            self.logger.log(Record::warn("auto_reload is not supported on this platform, the config files are not watched"));
+            return;
+        }
+        let mut dirs: Vec<PathBuf> = files.iter().filter_map(|file| file.parent()).map(Path::to_path_buf).collect();
+        // The directory of a glob is only watched once it exists
+        dirs.extend(includes.iter().filter_map(|include| include.parent()).flat_map(glob::expand).filter(|dir| dir.is_dir()));
+        dirs.sort();
+        dirs.dedup();
+        self.watched = (files, includes);
+        match Inotify::new(&dirs) {
+            Ok(watcher) => self.watcher = Some(watcher),
+            Err(err) => self.logger.log(Record::error(format!("Failed to watch the config files, auto_reload is disabled: {err}"))),
+        }
+    }
+
     fn reload(&mut self) {
-        self.logger.log("Reloading config file");
-        let new_programs = match Parsing::parse(&self.config_file_path) {
-            Ok(programs) => programs,
+        self.logger.log(Record::info("Reloading config file"));
+        let new_programs = match Parsing::parse(&self.config_file_path, &self.overrides) {
+            Ok(Parsed { settings, programs, files, includes, .. }) => {
+                let childlogdir = settings.childlogdir();
+                if let Err(err) = fs::create_dir_all(&childlogdir) {
+                    self.logger.log(Record::error(format!("Failed to reload config file: childlogdir '{}': {err}", childlogdir.display())));
+                    return;
+                }
+                self.apply_settings(settings);
+                self.watch(files, includes);
+                programs
+            },
             Err(err) => {
-                self.logger.log(&format!("Failed to reload config file: {err}"));
+                self.logger.log(Record::error(format!("Failed to reload config file: {err}")));
                 return;
             }
         };
@@ -441,12 +923,27 @@ impl Monitor {
         for (name, mut program) in new_programs {
             if self.programs.contains_key(&name) {
                 // 2. Check all progs and if the conf hasn't changed do nothing
-                if self.programs.iter().find(|e| e.0 == &name).unwrap().1.config == program.config {
+                let config = &self.programs.iter().find(|e| e.0 == &name).unwrap().1.config;
+                if *config == program.config {
                     continue;
                 } else {
-                    // 3. If something has changed then restart the procs with the new config
+                    // 3. If only the scheduling settings changed, apply them to the running procs
+                    if config.eq_except_scheduling(&program.config) {
+                        // The running program is kept, rebuilding it would reopen its outputs
+                        let applied = self.apply_scheduling(&name, &program).and_then(|_| {
+                            self.programs.get_mut(&name).ok_or("program is not loaded")?.set_scheduling(program.config.clone())
+                        });
+                        match applied {
+                            Ok(()) => {
+                                self.logger.log(Record::info("Applied scheduling settings to the running processus").program(&name));
+                                continue;
+                            },
+                            Err(err) => self.logger.log(Record::warn(format!("Failed to apply scheduling settings, restarting it: {err}")).program(&name)),
+                        }
+                    }
+                    // 4. If something else has changed then restart the procs with the new config
                     if let Err(err) = program.build_command() {
-                        eprintln!("Program {name}: {err}");
+                        self.logger.log(Record::error(format!("Invalid configuration: {err}")).program(&name));
                         continue;
                     }
                     self.stop_command(vec!(name.to_owned()));
@@ -457,13 +954,13 @@ impl Monitor {
                     self.programs.insert(Program::prefix_name(INACTIVE_FLAG, name), program);
                 }
             } else {
-                // 4. If some new programs appeared we start tracking them and start if necessery
+                // 5. If some new programs appeared we start tracking them and start if necessery
                 if let Err(err) = program.build_command() {
-                    eprintln!("Program {name}: {err}");
+                    self.logger.log(Record::error(format!("Invalid configuration: {err}")).program(&name));
                     continue;
                 }
-                for _ in 0..program.config.numprocs {
-                    self.processus.push(Processus::new(&name, &program));
+                for num in 0..program.config.numprocs {
+                    self.processus.push(Processus::new(&name, num, &program));
                 }
                 self.programs.insert(name.to_owned(), program);
                 let program = self.programs.get(&name).unwrap();
@@ -1,21 +1,33 @@
-use std::{str::FromStr, error::Error};
+use std::{str::FromStr, error::Error, time::Duration};
+use regex::Regex;
 
+use super::logger::Record;
+use super::output::Stream;
 use super::processus::Status;
 use super::processus::id::Id;
 
 #[derive(Debug)]
 pub enum Instruction {
     Status,
+    Details(Vec<String>),
+    Env(Vec<String>),
     Start(Vec<String>),
     Stop(Vec<String>),
     Restart(Vec<String>),
     Reload,
+    Send(String, Option<usize>, String),
+    Tail(String, Option<usize>, Stream, usize, bool),
+    StopFollowing,
+    LastLog(String, Option<usize>),
+    Grep(String, Option<usize>, Regex, Option<Duration>, Option<Stream>),
     RemoveProcessus(Id),
     StartProcessus(Id),
     ResetProcessus(Id),
     RetryStartProcessus(Id),
     SetStatus(Id, Status),
     KillProcessus(Id),
+    /// Record of a thread of the monitor, logged by the monitor
+    Log(Record),
     Exit,
 }
 
@@ -28,12 +40,87 @@ impl FromStr for Instruction {
         match command_name {
             "exit" | "Exit" => Ok(Instruction::Exit),
             "status" | "Status" => Ok(Instruction::Status),
+            "details" | "Details" => Ok(Instruction::Details(parts.map(|s| s.to_string()).collect())),
+            "env" | "Env" => Ok(Instruction::Env(parts.map(|s| s.to_string()).collect())),
             "start" | "Start" => Ok(Instruction::Start(parts.map(|s| s.to_string()).collect())),
             "stop" | "Stop" => Ok(Instruction::Stop(parts.map(|s| s.to_string()).collect())),
             "restart" | "Restart" => Ok(Instruction::Restart(parts.map(|s| s.to_string()).collect())),
             "reload" | "Reload" => Ok(Instruction::Reload),
+            "send" | "Send" => {
+                let mut parts = s.trim().splitn(3, char::is_whitespace).skip(1);
+                let (name, num) = split_target(parts.next().ok_or("Missing program name")?)?;
+                Ok(Instruction::Send(name, num, parts.next().unwrap_or_default().trim_start().to_string()))
+            },
+            "tail" | "Tail" => {
+                let (name, num) = split_target(parts.next().ok_or("Missing program name")?)?;
+                let mut stream = Stream::Stdout;
+                let mut lines = 10;
+                let mut follow = false;
+                while let Some(part) = parts.next() {
+                    match part {
+                        "stdout" => stream = Stream::Stdout,
+                        "stderr" => stream = Stream::Stderr,
+                        "-f" => follow = true,
+                        "-n" => lines = parts.next().ok_or("Missing number of lines")?.parse().map_err(|_| "Invalid number of lines")?,
+                        _ => Err(format!("Unknown tail option: {part}"))?,
+                    }
+                }
+                Ok(Instruction::Tail(name, num, stream, lines, follow))
+            },
+            "lastlog" | "Lastlog" => {
+                let (name, num) = split_target(parts.next().ok_or("Missing program name")?)?;
+                Ok(Instruction::LastLog(name, num))
+            },
+            "grep" | "Grep" => {
+                let (name, num) = split_target(parts.next().ok_or("Missing program name")?)?;
+                let mut pattern = Vec::new();
+                let mut since = None;
+                let mut stream = None;
+                while let Some(part) = parts.next() {
+                    match part {
+                        "--since" => since = Some(parse_duration(parts.next().ok_or("Missing duration")?)?),
+                        "--stream" => stream = match parts.next() {
+                            Some("stdout") => Some(Stream::Stdout),
+                            Some("stderr") => Some(Stream::Stderr),
+                            _ => Err("Expected stdout or stderr after --stream")?,
+                        },
+                        _ => pattern.push(part),
+                    }
+                }
+                if pattern.is_empty() {
+                    Err("Missing pattern")?;
+                }
+                let pattern = Regex::new(&pattern.join(" ")).map_err(|err| format!("Invalid pattern: {err}"))?;
+                Ok(Instruction::Grep(name, num, pattern, since, stream))
+            },
             _ => Err("Unknown command".into()),
         }
     }
 }
 
+
+/// Splits `name:n` into the program name and the instance number
+pub fn split_target(target: &str) -> Result<(String, Option<usize>), Box<dyn Error>> {
+    match target.rsplit_once(':') {
+        Some((name, num)) => Ok((name.to_string(), Some(num.parse().map_err(|_| format!("Invalid instance number: {num}"))?))),
+        None => Ok((target.to_string(), None)),
+    }
+}
+
+/// Parses `30s`, `10m`, `2h` or `1d`, a number without unit being seconds
+pub fn parse_duration(duration: &str) -> Result<Duration, Box<dyn Error>> {
+    let (value, unit) = match duration.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => duration.split_at(index),
+        None => (duration, "s"),
+    };
+    let value: u64 = value.parse().map_err(|_| format!("Invalid duration: {duration}"))?;
+    let unit = match unit {
+        "s" => 1,
+        "m" => 60,
+        "h" => 60 * 60,
+        "d" => 24 * 60 * 60,
+        _ => Err(format!("Invalid duration: {duration}, expected a unit among s, m, h and d"))?,
+    };
+    let seconds = value.checked_mul(unit).ok_or_else(|| format!("Invalid duration: {duration} is too long"))?;
+    Ok(Duration::from_secs(seconds))
+}
@@ -1,29 +1,314 @@
-use std::{io, fs::File, error::Error};
+use std::{io::{self, Write}, fs::{File, OpenOptions}, error::Error, fmt, path::{Path, PathBuf}, str::FromStr};
+use std::process::ExitStatus;
+use std::os::unix::process::ExitStatusExt;
+use serde::Deserialize;
 
+use crate::signal::Signal;
+use crate::sys::Libc;
+
+use super::output::syslog::{Severity, Syslog, SyslogConfig};
+use super::processus::Processus;
+
+const TEXT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+const JSON_TIME_FORMAT: &str = "%Y-%m-%dT%H:%M:%S%z";
+
+/// Severity of a record, ordered from the most to the least important
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, PartialOrd)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Error,
+    Warn,
+    Info,
+    Debug,
+}
+
+impl fmt::Display for Level {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Level::Error => write!(f, "error"),
+            Level::Warn => write!(f, "warn"),
+            Level::Info => write!(f, "info"),
+            Level::Debug => write!(f, "debug"),
+        }
+    }
+}
+
+impl FromStr for Level {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "error" => Ok(Level::Error),
+            "warn" => Ok(Level::Warn),
+            "info" => Ok(Level::Info),
+            "debug" => Ok(Level::Debug),
+            _ => Err(format!("invalid log level '{s}', expected error, warn, info or debug").into()),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("invalid log format '{s}', expected text or json").into()),
+        }
+    }
+}
+
+/// Settings of the supervisor log, sent to syslog instead of the file if `syslog` is set.
+/// On `reopen_signal` the log and the output files of the programs are opened again.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoggerConfig {
+    pub path: PathBuf,
+    pub level: Level,
+    pub format: LogFormat,
+    pub syslog: Option<SyslogConfig>,
+    pub reopen_signal: Signal,
+}
+
+impl Default for LoggerConfig {
+    fn default() -> Self {
+        Self {
+            path: PathBuf::from("taskmaster.log"),
+            level: Level::Info,
+            format: LogFormat::Text,
+            syslog: None,
+            reopen_signal: Signal::SIGUSR2,
+        }
+    }
+}
+
+impl From<Level> for Severity {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::Error => Severity::Error,
+            Level::Warn => Severity::Warning,
+            Level::Info => Severity::Info,
+            Level::Debug => Severity::Debug,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Value {
+    Text(String),
+    Number(i64),
+}
+
+/// One entry of the supervisor log, a message with structured fields
+#[derive(Debug, Clone, PartialEq)]
+pub struct Record {
+    level: Level,
+    message: String,
+    fields: Vec<(&'static str, Value)>,
+    output: Vec<String>,
+}
+
+impl Record {
+    pub fn new(level: Level, message: impl Into<String>) -> Self {
+        Self {
+            level,
+            message: message.into(),
+            fields: Vec::new(),
+            output: Vec::new(),
+        }
+    }
+
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(Level::Error, message)
+    }
+
+    pub fn warn(message: impl Into<String>) -> Self {
+        Self::new(Level::Warn, message)
+    }
+
+    pub fn info(message: impl Into<String>) -> Self {
+        Self::new(Level::Info, message)
+    }
+
+    pub fn debug(message: impl Into<String>) -> Self {
+        Self::new(Level::Debug, message)
+    }
+
+    pub fn program(mut self, name: &str) -> Self {
+        self.fields.push(("program", Value::Text(name.to_owned())));
+        self
+    }
+
+    /// Program, instance, id, pid and status of a processus
+    pub fn processus(mut self, processus: &Processus) -> Self {
+        self = self.program(&processus.name);
+        self.fields.push(("instance", Value::Number(processus.num as i64)));
+        self.fields.push(("id", Value::Number(processus.id.value() as i64)));
+        if let Some(child) = &processus.child {
+            self.fields.push(("pid", Value::Number(child.id() as i64)));
+        }
+        self.fields.push(("status", Value::Text(processus.status.to_string().trim().to_owned())));
+        self
+    }
+
+    pub fn exit_status(mut self, status: ExitStatus) -> Self {
+        if let Some(code) = status.code() {
+            self.fields.push(("exit_code", Value::Number(code as i64)));
+        }
+        if let Some(signal) = status.signal() {
+            self.fields.push(("signal", Value::Number(signal as i64)));
+        }
+        self
+    }
+
+    /// Recent output of the processus, written on the lines following the record
+    pub fn output(mut self, lines: Vec<String>) -> Self {
+        self.output = lines;
+        self
+    }
+
+    fn text(&self) -> String {
+        let mut text = self.message.to_owned();
+        for (key, value) in &self.fields {
+            match value {
+                Value::Text(value) if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '"') => {
+                    text.push_str(&format!(" {key}={value:?}"));
+                },
+                Value::Text(value) => text.push_str(&format!(" {key}={value}")),
+                Value::Number(value) => text.push_str(&format!(" {key}={value}")),
+            }
+        }
+        text
+    }
+
+    fn syslog_fields(&self) -> Vec<(&str, String)> {
+        let mut fields: Vec<(&str, String)> = self.fields.iter().map(|(key, value)| match value {
+            Value::Text(value) => (*key, value.to_owned()),
+            Value::Number(value) => (*key, value.to_string()),
+        }).collect();
+        if !self.output.is_empty() {
+            fields.push(("output", self.output.join("\n")));
+        }
+        fields
+    }
+
+    fn json(&self, time: &str) -> String {
+        let mut json = format!("{{\"time\":{},\"level\":\"{}\",\"message\":{}", Self::json_string(time), self.level, Self::json_string(&self.message));
+        for (key, value) in &self.fields {
+            match value {
+                Value::Text(value) => json.push_str(&format!(",\"{key}\":{}", Self::json_string(value))),
+                Value::Number(value) => json.push_str(&format!(",\"{key}\":{value}")),
+            }
+        }
+        if !self.output.is_empty() {
+            let lines: Vec<String> = self.output.iter().map(|line| Self::json_string(line)).collect();
+            json.push_str(&format!(",\"output\":[{}]", lines.join(",")));
+        }
+        json.push('}');
+        json
+    }
+
+    fn json_string(value: &str) -> String {
+        let mut escaped = String::from('"');
+        for c in value.chars() {
+            match c {
+                '"' => escaped.push_str("\\\""),
+                '\\' => escaped.push_str("\\\\"),
+                '\n' => escaped.push_str("\\n"),
+                '\r' => escaped.push_str("\\r"),
+                '\t' => escaped.push_str("\\t"),
+                c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+                c => escaped.push(c),
+            }
+        }
+        escaped.push('"');
+        escaped
+    }
+}
+
+/// Supervisor log written to a file or sent to syslog, records below the configured
+/// level are dropped and warnings and errors are also reported on taskmaster's error output.
 pub struct Logger {
-    output: Box<dyn io::Write + Send + Sync>,
+    output: Option<Box<dyn io::Write + Send + Sync>>,
+    path: Option<PathBuf>,
+    syslog: Option<Syslog>,
+    level: Level,
+    format: LogFormat,
 }
 
 impl Default for Logger {
     fn default() -> Self {
         Self {
-            output: Box::new(io::stdout()),
+            output: Some(Box::new(io::stdout())),
+            path: None,
+            syslog: None,
+            level: Level::Info,
+            format: LogFormat::Text,
         }
     }
 }
 
 impl Logger {
-    pub fn new(file_path: &str) -> Result<Self, Box<dyn Error>> {
-        let output = match File::create(file_path) {
-            Ok(file) => Box::new(file),
-            Err(_) => Err("Failed to create/open the log file")?,
-        };
+    pub fn new(config: &LoggerConfig) -> Result<Self, Box<dyn Error>> {
+        if let Some(syslog) = &config.syslog {
+            return Ok(Self {
+                output: None,
+                path: None,
+                syslog: Some(Syslog::connect(syslog, "taskmaster").map_err(|err| format!("Failed to connect to syslog: {err}"))?),
+                level: config.level,
+                format: config.format,
+            });
+        }
         Ok(Self {
-            output
+            output: Some(Box::new(Self::open(&config.path)?)),
+            path: Some(config.path.to_owned()),
+            syslog: None,
+            level: config.level,
+            format: config.format,
         })
     }
 
-    pub fn log(&mut self, msg: &str) {
-        writeln!(self.output, "{msg}").ok();
+    /// Opens the log file again, it is kept open if that fails
+    pub fn reopen(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(path) = &self.path {
+            self.output = Some(Box::new(Self::open(path)?));
+        }
+        Ok(())
+    }
+
+    fn open(path: &Path) -> Result<File, Box<dyn Error>> {
+        OpenOptions::new().create(true).append(true).open(path)
+            .map_err(|err| format!("Failed to open the log file '{}': {err}", path.display()).into())
+    }
+
+    pub fn log(&mut self, record: Record) {
+        if record.level <= Level::Warn {
+            eprintln!("{}: {}", record.level, record.text());
+        }
+        if record.level > self.level {
+            return;
+        }
+        if let Some(syslog) = &self.syslog {
+            let message = if syslog.is_structured() { record.message.to_owned() } else { record.text() };
+            syslog.send(record.level.into(), None, &message, &record.syslog_fields()).ok();
+        }
+        if let Some(output) = &mut self.output {
+            let line = match self.format {
+                LogFormat::Text => {
+                    let mut line = format!("{} {:<5} {}", Libc::strftime(TEXT_TIME_FORMAT), record.level.to_string().to_uppercase(), record.text());
+                    record.output.iter().for_each(|output| line.push_str(&format!("\n    | {output}")));
+                    line
+                },
+                LogFormat::Json => record.json(&Libc::strftime(JSON_TIME_FORMAT)),
+            };
+            writeln!(output, "{line}").ok();
+        }
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,414 @@
+use std::{collections::VecDeque, error::Error, fmt, thread::{self, JoinHandle}};
+use std::io::{BufRead, BufReader, Read};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+use crate::sys::Libc;
+
+use super::parsing::RateLimitConfig;
+
+use self::log_file::LogFile;
+use self::sink::{Line, Sink};
+
+pub mod log_file;
+pub mod sink;
+pub mod syslog;
+
+const DEFAULT_FORMAT: &str = "{time} {program}:{instance} [{stream}] {line}";
+const DEFAULT_TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stream {
+    Stdout,
+    Stderr,
+}
+
+impl fmt::Display for Stream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Stream::Stdout => write!(f, "stdout"),
+            Stream::Stderr => write!(f, "stderr"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Field {
+    Text(String),
+    Time,
+    Program,
+    Instance,
+    Pid,
+    Stream,
+    Line,
+}
+
+/// Format of the captured lines, with the `{time}`, `{program}`, `{instance}`,
+/// `{pid}`, `{stream}` and `{line}` fields, `{{` and `}}` being literal braces.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineFormat {
+    fields: Vec<Field>,
+    time_format: String,
+}
+
+impl LineFormat {
+    pub fn new(format: &str, time_format: &str) -> Result<Self, Box<dyn Error>> {
+        let format = if format.is_empty() { DEFAULT_FORMAT } else { format };
+        let time_format = if time_format.is_empty() { DEFAULT_TIME_FORMAT } else { time_format };
+        let mut fields = Vec::new();
+        let mut text = String::new();
+        let mut chars = format.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('{') if name.is_empty() => {
+                                text.push('{');
+                                break;
+                            },
+                            Some('}') => {
+                                if !text.is_empty() {
+                                    fields.push(Field::Text(std::mem::take(&mut text)));
+                                }
+                                fields.push(Self::field(&name)?);
+                                break;
+                            },
+                            Some(c) => name.push(c),
+                            None => Err("unterminated '{' in output_format")?,
+                        }
+                    }
+                },
+                '}' => match chars.next() {
+                    Some('}') => text.push('}'),
+                    _ => Err("single '}' in output_format, use '}}'")?,
+                },
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            fields.push(Field::Text(text));
+        }
+        Ok(Self {
+            fields,
+            time_format: time_format.to_owned(),
+        })
+    }
+
+    fn field(name: &str) -> Result<Field, Box<dyn Error>> {
+        match name {
+            "time" => Ok(Field::Time),
+            "program" => Ok(Field::Program),
+            "instance" => Ok(Field::Instance),
+            "pid" => Ok(Field::Pid),
+            "stream" => Ok(Field::Stream),
+            "line" => Ok(Field::Line),
+            _ => Err(format!("unknown field '{{{name}}}' in output_format").into()),
+        }
+    }
+
+    pub fn format(&self, program: &str, instance: usize, pid: u32, stream: Stream, line: &str) -> String {
+        let mut formatted = String::new();
+        for field in &self.fields {
+            match field {
+                Field::Text(text) => formatted.push_str(text),
+                Field::Time => formatted.push_str(&Libc::strftime(&self.time_format)),
+                Field::Program => formatted.push_str(program),
+                Field::Instance => formatted.push_str(&instance.to_string()),
+                Field::Pid => formatted.push_str(&pid.to_string()),
+                Field::Stream => formatted.push_str(&stream.to_string()),
+                Field::Line => formatted.push_str(line),
+            }
+        }
+        formatted
+    }
+
+    /// Reads the fields back from a line written with the format, `None` if it doesn't match.
+    /// Fields other than the time must be followed by some text to know where they end.
+    pub fn parse<'a>(&self, formatted: &'a str) -> Option<ParsedLine<'a>> {
+        let mut parsed = ParsedLine::default();
+        let mut rest = formatted;
+        for (index, field) in self.fields.iter().enumerate() {
+            let len = match (field, self.fields.get(index + 1)) {
+                (Field::Text(text), _) => {
+                    rest = rest.strip_prefix(text.as_str())?;
+                    continue;
+                },
+                (Field::Time, _) => {
+                    let (time, len) = Libc::strptime(rest, &self.time_format)?;
+                    parsed.time = Some(time);
+                    len
+                },
+                (Field::Line, Some(Field::Text(text))) => rest.rfind(text.as_str())?,
+                (_, Some(Field::Text(text))) => rest.find(text.as_str())?,
+                (_, None) => rest.len(),
+                (_, Some(_)) => return None,
+            };
+            let (value, next) = rest.split_at(len);
+            match field {
+                Field::Time => parsed.time_text = Some(value),
+                Field::Instance => parsed.instance = value.parse().ok(),
+                Field::Line => parsed.line = Some(value),
+                _ => (),
+            }
+            rest = next;
+        }
+        Some(parsed)
+    }
+}
+
+/// Fields of a captured line read back by `LineFormat::parse`, the ones missing from the format are `None`
+#[derive(Debug, Default)]
+pub struct ParsedLine<'a> {
+    pub time: Option<i64>,
+    pub time_text: Option<&'a str>,
+    pub instance: Option<usize>,
+    pub line: Option<&'a str>,
+}
+
+/// Last lines written by an instance on both streams, up to `capacity` bytes
+#[derive(Debug)]
+pub struct RecentOutput {
+    lines: VecDeque<(Stream, String)>,
+    size: usize,
+    capacity: usize,
+}
+
+impl RecentOutput {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            lines: VecDeque::new(),
+            size: 0,
+            capacity,
+        }
+    }
+
+    pub fn push(&mut self, stream: Stream, mut line: String) {
+        if line.len() > self.capacity {
+            let mut start = line.len() - self.capacity;
+            while !line.is_char_boundary(start) {
+                start += 1;
+            }
+            line.drain(..start);
+        }
+        self.size += line.len();
+        self.lines.push_back((stream, line));
+        while self.size > self.capacity {
+            match self.lines.pop_front() {
+                Some((_, line)) => self.size -= line.len(),
+                None => break,
+            }
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.lines.clear();
+        self.size = 0;
+    }
+
+    /// Last `count` lines of `stream`, or of both streams if it's `None`, all of them if `count` is `None`
+    pub fn tail(&self, count: Option<usize>, stream: Option<Stream>) -> Vec<String> {
+        let lines: Vec<&String> = self.lines.iter()
+            .filter(|(line_stream, _)| stream.is_none_or(|stream| stream == *line_stream))
+            .map(|(_, line)| line)
+            .collect();
+        let skip = count.map_or(0, |count| lines.len().saturating_sub(count));
+        lines.into_iter().skip(skip).cloned().collect()
+    }
+}
+
+/// Length cap and rate limit of a stream, with the number of lines dropped or truncated
+#[derive(Debug)]
+pub struct OutputLimit {
+    max_line_length: usize,
+    rate: Option<RateLimitConfig>,
+    window: Instant,
+    lines: u64,
+    bytes: u64,
+    // Dropped in the current window, then in the previous ones until reported
+    pending: u64,
+    unreported: u64,
+    last_pid: u32,
+    pub dropped: u64,
+    pub truncated: u64,
+}
+
+impl OutputLimit {
+    pub fn new(max_line_length: usize, rate: Option<RateLimitConfig>) -> Self {
+        Self {
+            max_line_length,
+            rate,
+            window: Instant::now(),
+            lines: 0,
+            bytes: 0,
+            pending: 0,
+            unreported: 0,
+            last_pid: 0,
+            dropped: 0,
+            truncated: 0,
+        }
+    }
+
+    pub fn max_line_length(&self) -> usize {
+        self.max_line_length
+    }
+
+    /// The line as it has to be written, or `None` if it is over the budget
+    fn admit(&mut self, pid: u32, line: &str) -> Option<String> {
+        self.last_pid = pid;
+        let mut line = line.to_owned();
+        if self.max_line_length > 0 && line.len() > self.max_line_length {
+            let mut end = self.max_line_length;
+            while !line.is_char_boundary(end) {
+                end -= 1;
+            }
+            line.truncate(end);
+            line.push_str(" [truncated]");
+            self.truncated += 1;
+        }
+        self.next_window();
+        if let Some(rate) = &self.rate {
+            let over_lines = rate.lines > 0 && self.lines >= rate.lines;
+            let over_bytes = rate.bytes > 0 && self.bytes + line.len() as u64 > rate.bytes;
+            if over_lines || over_bytes {
+                self.pending += 1;
+                self.dropped += 1;
+                return None;
+            }
+            self.lines += 1;
+            self.bytes += line.len() as u64;
+        }
+        Some(line)
+    }
+
+    /// Number of lines dropped in the windows that are over
+    fn report(&mut self) -> Option<u64> {
+        self.next_window();
+        (self.unreported > 0).then(|| std::mem::take(&mut self.unreported))
+    }
+
+    fn next_window(&mut self) {
+        let interval = self.rate.as_ref().map_or(1, |rate| rate.interval);
+        if self.window.elapsed() >= Duration::from_secs(interval) {
+            self.window = Instant::now();
+            self.lines = 0;
+            self.bytes = 0;
+            self.unreported += std::mem::take(&mut self.pending);
+        }
+    }
+}
+
+/// Destination of one stream of an instance, the sinks are opened by taskmaster
+/// and the child writes into a pipe read by a capture thread.
+#[derive(Debug, Clone)]
+pub struct Output {
+    pub program: String,
+    pub instance: usize,
+    pub stream: Stream,
+    pub format: LineFormat,
+    pub sinks: Vec<Arc<dyn Sink>>,
+    pub limit: Option<Arc<Mutex<OutputLimit>>>,
+    /// Set by `tail -f`, the captured lines are also printed on taskmaster's output
+    pub follow: Arc<AtomicBool>,
+}
+
+impl Output {
+    /// Files written by the sinks of the stream
+    pub fn files(&self) -> impl Iterator<Item = &Mutex<LogFile>> {
+        self.sinks.iter().filter_map(|sink| sink.file())
+    }
+
+    /// Reads the child's stream line by line until it is closed. With `max_line_length`
+    /// only the start of a longer line is kept in memory, the rest is skipped.
+    pub fn capture<R: Read + Send + 'static>(&self, reader: R, pid: u32) -> JoinHandle<()> {
+        let output = self.clone();
+        let max_line_length = match self.limit.as_ref().map(|limit| limit.lock()) {
+            Some(Ok(limit)) if limit.max_line_length() > 0 => limit.max_line_length() as u64 + 1,
+            _ => u64::MAX,
+        };
+        thread::spawn(move || {
+            let mut reader = BufReader::new(reader);
+            let mut buffer = Vec::new();
+            loop {
+                buffer.clear();
+                match reader.by_ref().take(max_line_length).read_until(b'\n', &mut buffer) {
+                    Ok(0) | Err(_) => break,
+                    Ok(_) => {
+                        output.write_line(pid, &buffer);
+                        if !buffer.ends_with(b"\n") && Self::skip_line(&mut reader).is_err() {
+                            break;
+                        }
+                    },
+                }
+            }
+        })
+    }
+
+    // Consumes the input up to the end of the line without keeping it
+    fn skip_line<R: Read>(reader: &mut BufReader<R>) -> std::io::Result<()> {
+        loop {
+            let available = reader.fill_buf()?;
+            if available.is_empty() {
+                return Ok(());
+            }
+            match available.iter().position(|&c| c == b'\n') {
+                Some(index) => {
+                    reader.consume(index + 1);
+                    return Ok(());
+                },
+                None => {
+                    let len = available.len();
+                    reader.consume(len);
+                },
+            }
+        }
+    }
+
+    /// Writes the "suppressed N lines" marker once the window in which lines were dropped is over
+    pub fn report_suppressed(&self) {
+        let report = match self.limit.as_ref().map(|limit| limit.lock()) {
+            Some(Ok(mut limit)) => limit.report().map(|count| (limit.last_pid, count)),
+            _ => None,
+        };
+        if let Some((pid, count)) = report {
+            self.write(pid, &format!("taskmaster: suppressed {count} lines"));
+        }
+    }
+
+    fn write_line(&self, pid: u32, buffer: &[u8]) {
+        self.report_suppressed();
+        let line = String::from_utf8_lossy(buffer);
+        let line = line.trim_end_matches(['\n', '\r']);
+        let line = match self.limit.as_ref().map(|limit| limit.lock()) {
+            Some(Ok(mut limit)) => match limit.admit(pid, line) {
+                Some(line) => line,
+                None => return,
+            },
+            _ => line.to_owned(),
+        };
+        self.write(pid, &line);
+    }
+
+    fn write(&self, pid: u32, line: &str) {
+        let formatted = self.format.format(&self.program, self.instance, pid, self.stream, line);
+        if self.follow.load(Ordering::SeqCst) {
+            println!("{formatted}");
+        }
+        let line = Line {
+            program: &self.program,
+            instance: self.instance,
+            pid,
+            stream: self.stream,
+            text: line,
+            formatted: &formatted,
+        };
+        for sink in &self.sinks {
+            if let Err(err) = sink.write(&line) {
+                eprintln!("Program {}: {err}", self.program);
+            }
+        }
+    }
+}
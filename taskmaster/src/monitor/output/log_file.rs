@@ -0,0 +1,134 @@
+use std::{fs::{self, File, OpenOptions}, io::{self, Read, Seek, SeekFrom, Write}, os::unix::fs::MetadataExt, path::{Path, PathBuf}, sync::Mutex};
+
+// Several handles can write to the same file, the size check and the rotation are done under this lock
+static ROTATION: Mutex<()> = Mutex::new(());
+
+/// Log file written by taskmaster, rotated to `.1`, `.2`... once it grows past `maxbytes`.
+/// Without backups the file is truncated instead, and a `maxbytes` of 0 disables the rotation.
+/// The file is always written in append mode, so that instances sharing it don't clobber each other.
+#[derive(Debug)]
+pub struct LogFile {
+    path: PathBuf,
+    file: File,
+    maxbytes: u64,
+    backups: usize,
+}
+
+impl LogFile {
+    pub fn open(path: &Path, truncate: bool, maxbytes: u64, backups: usize) -> io::Result<Self> {
+        if truncate {
+            File::create(path)?;
+        }
+        Ok(Self {
+            path: path.to_owned(),
+            file: Self::open_append(path)?,
+            maxbytes,
+            backups,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes a whole line, rotating the file before it if the line doesn't fit
+    pub fn write_line(&mut self, line: &[u8]) -> io::Result<()> {
+        if self.maxbytes == 0 {
+            return self.file.write_all(line);
+        }
+        let _guard = ROTATION.lock().unwrap_or_else(|err| err.into_inner());
+        self.reopen_if_replaced()?;
+        let size = self.file.metadata()?.len();
+        if size > 0 && size + line.len() as u64 > self.maxbytes {
+            self.rotate()?;
+        }
+        self.file.write_all(line)
+    }
+
+    /// Opens the path again, for files moved away by an external tool like logrotate
+    pub fn reopen(&mut self) -> io::Result<()> {
+        self.file = Self::open_append(&self.path)?;
+        Ok(())
+    }
+
+    pub fn backup_path(&self, index: usize) -> PathBuf {
+        let mut path = self.path.clone().into_os_string();
+        path.push(format!(".{index}"));
+        PathBuf::from(path)
+    }
+
+    /// Paths of the existing backups and of the file, from the oldest to the current one
+    pub fn history(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = (1..=self.backups).rev().map(|index| self.backup_path(index)).filter(|path| path.exists()).collect();
+        paths.push(self.path.clone());
+        paths
+    }
+
+    /// Last `count` lines of the file, continuing into the backups if it is too short
+    pub fn tail(&self, count: usize) -> io::Result<Vec<String>> {
+        let mut lines = Vec::new();
+        let mut index = 0;
+        while lines.len() < count {
+            let path = if index == 0 { self.path.clone() } else { self.backup_path(index) };
+            let mut file = match File::open(&path) {
+                Ok(file) => file,
+                Err(err) if err.kind() == io::ErrorKind::NotFound && index > 0 => break,
+                Err(err) => return Err(err),
+            };
+            let mut older = Self::tail_file(&mut file, count - lines.len())?;
+            older.append(&mut lines);
+            lines = older;
+            index += 1;
+        }
+        Ok(lines)
+    }
+
+    fn tail_file(file: &mut File, count: usize) -> io::Result<Vec<String>> {
+        const BLOCK: u64 = 8192;
+        let mut end = file.seek(SeekFrom::End(0))?;
+        let mut content = Vec::new();
+        while end > 0 && content.iter().filter(|&&c| c == b'\n').count() <= count {
+            let start = end.saturating_sub(BLOCK);
+            let mut block = vec![0; (end - start) as usize];
+            file.seek(SeekFrom::Start(start))?;
+            file.read_exact(&mut block)?;
+            block.append(&mut content);
+            content = block;
+            end = start;
+        }
+        let content = String::from_utf8_lossy(&content);
+        let lines: Vec<&str> = content.lines().collect();
+        Ok(lines[lines.len().saturating_sub(count)..].iter().map(|line| line.to_string()).collect())
+    }
+
+    fn open_append(path: &Path) -> io::Result<File> {
+        OpenOptions::new().create(true).append(true).open(path)
+    }
+
+    // Another handle on the same path may have rotated the file
+    fn reopen_if_replaced(&mut self) -> io::Result<()> {
+        let current = self.file.metadata()?;
+        match fs::metadata(&self.path) {
+            Ok(metadata) if metadata.ino() == current.ino() && metadata.dev() == current.dev() => Ok(()),
+            _ => {
+                self.file = Self::open_append(&self.path)?;
+                Ok(())
+            },
+        }
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        if self.backups == 0 {
+            return self.file.set_len(0);
+        }
+        for index in (1..self.backups).rev() {
+            let from = self.backup_path(index);
+            if from.exists() {
+                fs::rename(&from, self.backup_path(index + 1))?;
+            }
+        }
+        fs::rename(&self.path, self.backup_path(1))?;
+        self.file = Self::open_append(&self.path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,194 @@
+use std::{collections::BTreeMap, error::Error, fmt, fs::File, io::{self, Write}, os::fd::{AsFd, AsRawFd}, process::ChildStdin};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::sys::Libc;
+
+use super::{RecentOutput, Stream};
+use super::log_file::LogFile;
+use super::syslog::{Severity, Syslog};
+
+// Stdin pipes of the running instances, fed by the `program` sinks
+static PROGRAM_INPUTS: Mutex<BTreeMap<(String, usize), Arc<Input>>> = Mutex::new(BTreeMap::new());
+
+// Lines that didn't fit in a full pipe are dropped and counted
+#[derive(Debug)]
+struct Input {
+    file: File,
+    dropped: AtomicU64,
+}
+
+/// Keeps a handle on the stdin pipe of an instance for the `program` sinks.
+/// The pipe is non-blocking so that a program not reading it doesn't hold the capture threads.
+pub fn register_input(program: &str, instance: usize, stdin: &ChildStdin) -> io::Result<()> {
+    let file = File::from(stdin.as_fd().try_clone_to_owned()?);
+    Libc::set_nonblocking(file.as_raw_fd())?;
+    let input = Arc::new(Input { file, dropped: AtomicU64::new(0) });
+    PROGRAM_INPUTS.lock().unwrap_or_else(|err| err.into_inner()).insert((program.to_owned(), instance), input);
+    Ok(())
+}
+
+pub fn unregister_input(program: &str, instance: usize) {
+    PROGRAM_INPUTS.lock().unwrap_or_else(|err| err.into_inner()).remove(&(program.to_owned(), instance));
+}
+
+/// A captured line, as read from the child and formatted with `output_format`
+pub struct Line<'a> {
+    pub program: &'a str,
+    pub instance: usize,
+    pub pid: u32,
+    pub stream: Stream,
+    pub text: &'a str,
+    pub formatted: &'a str,
+}
+
+/// Destination of the captured lines of a stream
+pub trait Sink: fmt::Debug + Send + Sync {
+    fn write(&self, line: &Line) -> Result<(), Box<dyn Error>>;
+
+    /// File written by the sink, for `tail`, `status` and reopening
+    fn file(&self) -> Option<&Mutex<LogFile>> {
+        None
+    }
+}
+
+#[derive(Debug)]
+pub struct FileSink {
+    file: Mutex<LogFile>,
+}
+
+impl FileSink {
+    pub fn new(file: LogFile) -> Self {
+        Self {
+            file: Mutex::new(file),
+        }
+    }
+}
+
+impl Sink for FileSink {
+    fn write(&self, line: &Line) -> Result<(), Box<dyn Error>> {
+        let mut file = self.file.lock().unwrap_or_else(|err| err.into_inner());
+        file.write_line(format!("{}\n", line.formatted).as_bytes())
+            .map_err(|err| format!("failed to write to '{}': {err}", file.path().display()).into())
+    }
+
+    fn file(&self) -> Option<&Mutex<LogFile>> {
+        Some(&self.file)
+    }
+}
+
+/// Sends the raw line, the time and the program are fields of the message
+#[derive(Debug)]
+pub struct SyslogSink {
+    syslog: Syslog,
+}
+
+impl SyslogSink {
+    pub fn new(syslog: Syslog) -> Self {
+        Self {
+            syslog,
+        }
+    }
+}
+
+impl Sink for SyslogSink {
+    fn write(&self, line: &Line) -> Result<(), Box<dyn Error>> {
+        let severity = match line.stream {
+            Stream::Stdout => Severity::Info,
+            Stream::Stderr => Severity::Error,
+        };
+        let fields = [
+            ("program", line.program.to_owned()),
+            ("instance", line.instance.to_string()),
+            ("pid", line.pid.to_string()),
+            ("stream", line.stream.to_string()),
+        ];
+        self.syslog.send(severity, Some(line.pid), line.text, &fields)
+            .map_err(|err| format!("failed to send to syslog: {err}").into())
+    }
+}
+
+/// Buffer of recent output shared by the streams of the instance
+#[derive(Debug)]
+pub struct MemorySink {
+    recent: Arc<Mutex<RecentOutput>>,
+}
+
+impl MemorySink {
+    pub fn new(recent: Arc<Mutex<RecentOutput>>) -> Self {
+        Self {
+            recent,
+        }
+    }
+}
+
+impl Sink for MemorySink {
+    fn write(&self, line: &Line) -> Result<(), Box<dyn Error>> {
+        self.recent.lock().unwrap_or_else(|err| err.into_inner()).push(line.stream, line.formatted.to_owned());
+        Ok(())
+    }
+}
+
+/// Prints the lines on taskmaster's output
+#[derive(Debug)]
+pub struct TerminalSink;
+
+impl Sink for TerminalSink {
+    fn write(&self, line: &Line) -> Result<(), Box<dyn Error>> {
+        println!("{}", line.formatted);
+        Ok(())
+    }
+}
+
+/// Writes the raw line to the stdin of every running instance of `program`
+#[derive(Debug)]
+pub struct ProgramSink {
+    program: String,
+}
+
+impl ProgramSink {
+    pub fn new(program: &str) -> Self {
+        Self {
+            program: program.to_owned(),
+        }
+    }
+}
+
+impl Sink for ProgramSink {
+    /// Reports when an instance starts dropping lines and how many it dropped once it reads again
+    fn write(&self, line: &Line) -> Result<(), Box<dyn Error>> {
+        // The lock is released before writing, the monitor takes it to start and reap the instances
+        let inputs: Vec<(usize, Arc<Input>)> = PROGRAM_INPUTS.lock().unwrap_or_else(|err| err.into_inner()).iter()
+            .filter(|((program, _), _)| *program == self.program)
+            .map(|((_, instance), input)| (*instance, input.clone()))
+            .collect();
+        let text = format!("{}\n", line.text);
+        let mut problems = Vec::new();
+        for (instance, input) in inputs {
+            let target = format!("{}:{instance}", self.program);
+            match (&input.file).write(text.as_bytes()) {
+                Ok(written) if written == text.len() => {
+                    let dropped = input.dropped.swap(0, Ordering::SeqCst);
+                    if dropped > 0 {
+                        problems.push(format!("the stdin of {target} was full, {dropped} lines were dropped"));
+                    }
+                },
+                // Only lines longer than the atomic size of a pipe are cut, the rest is dropped
+                Ok(_) => {
+                    (&input.file).write_all(b"\n").ok();
+                    input.dropped.fetch_add(1, Ordering::SeqCst);
+                },
+                Err(err) if err.kind() == io::ErrorKind::WouldBlock => {
+                    if input.dropped.fetch_add(1, Ordering::SeqCst) == 0 {
+                        problems.push(format!("the stdin of {target} is full, dropping lines"));
+                    }
+                },
+                Err(err) => problems.push(format!("failed to write to the stdin of {target}: {err}")),
+            }
+        }
+        match problems.is_empty() {
+            true => Ok(()),
+            false => Err(problems.join(", ").into()),
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use std::{error::Error, io, os::unix::net::UnixDatagram, path::{Path, PathBuf}, process, str::FromStr};
+use serde::Deserialize;
+
+use crate::sys::Libc;
+
+const SYSLOG_SOCKET: &str = "/dev/log";
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+// Documentation enterprise number, the structured data of RFC 5424 requires one
+const SD_ID: &str = "taskmaster@32473";
+
+/// Wire format of the messages: RFC 5424 or RFC 3164 over the syslog socket,
+/// or the native protocol of journald with one field per structured value.
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    #[default]
+    Rfc5424,
+    Rfc3164,
+    Journald,
+}
+
+impl FromStr for Protocol {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rfc5424" => Ok(Protocol::Rfc5424),
+            "rfc3164" => Ok(Protocol::Rfc3164),
+            "journald" => Ok(Protocol::Journald),
+            _ => Err(format!("invalid syslog protocol '{s}', expected rfc5424, rfc3164 or journald").into()),
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+#[repr(u8)]
+pub enum Facility {
+    Kern = 0,
+    #[default]
+    User = 1,
+    Mail = 2,
+    Daemon = 3,
+    Auth = 4,
+    Syslog = 5,
+    Lpr = 6,
+    News = 7,
+    Uucp = 8,
+    Cron = 9,
+    Authpriv = 10,
+    Ftp = 11,
+    Local0 = 16,
+    Local1 = 17,
+    Local2 = 18,
+    Local3 = 19,
+    Local4 = 20,
+    Local5 = 21,
+    Local6 = 22,
+    Local7 = 23,
+}
+
+impl FromStr for Facility {
+    type Err = Box<dyn Error>;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        serde_yaml::from_str(s).map_err(|_| format!("invalid syslog facility '{s}'").into())
+    }
+}
+
+/// Severities of syslog, only the ones taskmaster uses
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum Severity {
+    Error = 3,
+    Warning = 4,
+    Info = 6,
+    Debug = 7,
+}
+
+/// Settings of a syslog destination, an empty `tag` is replaced by the
+/// program name and an empty `socket` by the usual path of the protocol.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct SyslogConfig {
+    pub protocol: Protocol,
+    pub facility: Facility,
+    pub tag: String,
+    pub socket: PathBuf,
+}
+
+/// Connected syslog or journald socket
+#[derive(Debug)]
+pub struct Syslog {
+    protocol: Protocol,
+    facility: Facility,
+    tag: String,
+    hostname: String,
+    socket: UnixDatagram,
+}
+
+impl Syslog {
+    pub fn connect(config: &SyslogConfig, default_tag: &str) -> Result<Self, Box<dyn Error>> {
+        let path = match config.socket.as_os_str().is_empty() {
+            true if config.protocol == Protocol::Journald => Path::new(JOURNALD_SOCKET),
+            true => Path::new(SYSLOG_SOCKET),
+            false => &config.socket,
+        };
+        let socket = UnixDatagram::unbound()?;
+        socket.connect(path).map_err(|err| format!("syslog socket '{}' {err}", path.display()))?;
+        Ok(Self {
+            protocol: config.protocol,
+            facility: config.facility,
+            tag: if config.tag.is_empty() { default_tag } else { &config.tag }.to_owned(),
+            hostname: Libc::gethostname().unwrap_or_else(|_| "-".to_owned()),
+            socket,
+        })
+    }
+
+    /// Whether the `fields` are sent, RFC 3164 only carries the message
+    pub fn is_structured(&self) -> bool {
+        self.protocol != Protocol::Rfc3164
+    }
+
+    /// Sends a message from the process `pid`, taskmaster's own pid if `None`
+    pub fn send(&self, severity: Severity, pid: Option<u32>, message: &str, fields: &[(&str, String)]) -> io::Result<()> {
+        let pid = pid.unwrap_or_else(process::id);
+        let priority = (self.facility as u8) * 8 + severity as u8;
+        let datagram = match self.protocol {
+            Protocol::Rfc5424 => {
+                let time = Libc::strftime("%Y-%m-%dT%H:%M:%S%z");
+                // The offset is written +hh:mm
+                let time = if time.len() > 2 { format!("{}:{}", &time[..time.len() - 2], &time[time.len() - 2..]) } else { time };
+                let data = if fields.is_empty() {
+                    "-".to_owned()
+                } else {
+                    let params: Vec<String> = fields.iter().map(|(key, value)| format!("{key}=\"{}\"", Self::escape_param(value))).collect();
+                    format!("[{SD_ID} {}]", params.join(" "))
+                };
+                format!("<{priority}>1 {time} {} {} {pid} - {data} {message}", self.hostname, self.tag).into_bytes()
+            },
+            Protocol::Rfc3164 => {
+                format!("<{priority}>{} {}[{pid}]: {message}", Libc::strftime("%b %e %H:%M:%S"), self.tag).into_bytes()
+            },
+            Protocol::Journald => {
+                let mut datagram = Vec::new();
+                Self::journald_field(&mut datagram, "MESSAGE", message);
+                Self::journald_field(&mut datagram, "PRIORITY", &(severity as u8).to_string());
+                Self::journald_field(&mut datagram, "SYSLOG_FACILITY", &(self.facility as u8).to_string());
+                Self::journald_field(&mut datagram, "SYSLOG_IDENTIFIER", &self.tag);
+                for (key, value) in fields {
+                    Self::journald_field(&mut datagram, &key.to_uppercase(), value);
+                }
+                datagram
+            },
+        };
+        self.socket.send(&datagram).map(|_| ())
+    }
+
+    fn escape_param(value: &str) -> String {
+        value.chars().fold(String::new(), |mut escaped, c| {
+            if matches!(c, '"' | '\\' | ']') {
+                escaped.push('\\');
+            }
+            escaped.push(c);
+            escaped
+        })
+    }
+
+    // Values with a newline use the binary form: name, newline, little endian length, value
+    fn journald_field(datagram: &mut Vec<u8>, key: &str, value: &str) {
+        datagram.extend_from_slice(key.as_bytes());
+        if value.contains('\n') {
+            datagram.push(b'\n');
+            datagram.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            datagram.push(b'=');
+        }
+        datagram.extend_from_slice(value.as_bytes());
+        datagram.push(b'\n');
+    }
+}
@@ -1,19 +1,48 @@
-use std::{collections::HashMap, fs};
+use std::{collections::HashMap, fmt, fs};
 use std::error::Error;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use serde::{Deserialize, Deserializer};
+use serde::de::DeserializeOwned;
+use serde_yaml::{Mapping, Value};
 
+use crate::monitor::output::{LineFormat, Stream};
+use crate::monitor::output::syslog::SyslogConfig;
 use crate::monitor::program::Program;
 use crate::signal::Signal;
+use crate::sys::Libc;
+use crate::sys::scheduling::{IoniceClass, Scheduling};
 
-#[derive(Deserialize, Debug, Default, PartialEq)]
+use self::cmd::Cmd;
+use self::format::Format;
+use self::interpolation::{Variables, escape};
+use self::settings::Settings;
+use self::sink::{FileSinkConfig, OutputConfig, SinkConfig, SinkName};
+use self::template::{EXTENDS, Templates};
+
+pub mod check;
+pub mod cmd;
+pub mod format;
+pub mod glob;
+pub mod ini;
+pub mod settings;
+pub mod sink;
+pub mod template;
+pub mod dotenv;
+pub mod interpolation;
+
+const AUTO_OUTPUT: &str = "AUTO";
+
+
+#[derive(Deserialize, Debug, PartialEq, Clone)]
 #[serde(deny_unknown_fields, default)]
 pub struct Config {
-    pub cmd: String,
+    pub cmd: Cmd,
+    pub shell: bool,
+    /// Name shared by programs to target them together
+    pub group: Option<String>,
     pub numprocs: usize,
     #[serde(deserialize_with = "umask_deserialize")]
     pub umask: u32,
-    #[serde(deserialize_with = "workingdir_deserialize")]
     pub workingdir: PathBuf,
     pub autostart: bool,
     #[serde(deserialize_with = "autorestart_deserialize")]
@@ -23,9 +52,148 @@ pub struct Config {
     pub starttime: usize,
     pub stopsignal: Signal,
     pub stoptime: usize,
-    pub stdout: PathBuf,
-    pub stderr: PathBuf,
+    pub stdout: OutputConfig,
+    pub stderr: OutputConfig,
+    pub redirect_stderr: bool,
+    pub stdin: StdinConfig,
+    pub stdout_mode: OutputMode,
+    pub stderr_mode: OutputMode,
+    #[serde(deserialize_with = "bytes_deserialize")]
+    pub stdout_maxbytes: u64,
+    pub stdout_backups: usize,
+    #[serde(deserialize_with = "bytes_deserialize")]
+    pub stderr_maxbytes: u64,
+    pub stderr_backups: usize,
+    pub output_format: String,
+    pub output_time_format: String,
+    /// Bytes of recent output kept in memory for each instance, 0 to disable
+    #[serde(deserialize_with = "bytes_deserialize")]
+    pub output_buffer: u64,
+    /// Longer lines are truncated, 0 to disable
+    #[serde(deserialize_with = "bytes_deserialize")]
+    pub max_line_length: u64,
+    pub log_rate_limit: Option<RateLimitConfig>,
+    pub stdout_syslog: bool,
+    pub stderr_syslog: bool,
+    pub syslog: SyslogConfig,
     pub env: HashMap<String, String>,
+    pub clear_env: bool,
+    pub env_passthrough: Vec<String>,
+    #[serde(deserialize_with = "paths_deserialize")]
+    pub env_file: Vec<PathBuf>,
+    pub landlock: Option<LandlockConfig>,
+    pub nice: Option<i32>,
+    pub ionice_class: Option<IoniceClass>,
+    pub ionice_level: Option<u32>,
+    pub cpu_affinity: Vec<usize>,
+    pub oom_score_adj: Option<i32>,
+}
+
+/// Values of the settings missing from the program, its templates and the `defaults`
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            cmd: Cmd::default(),
+            shell: false,
+            group: None,
+            numprocs: 1,
+            umask: 0o022,
+            workingdir: PathBuf::from("."),
+            autostart: false,
+            autorestart: "unexpected".to_owned(),
+            exitcodes: vec![0],
+            startretries: 3,
+            starttime: 1,
+            stopsignal: Signal::SIGTERM,
+            stoptime: 10,
+            stdout: OutputConfig::default(),
+            stderr: OutputConfig::default(),
+            redirect_stderr: false,
+            stdin: StdinConfig::default(),
+            stdout_mode: OutputMode::default(),
+            stderr_mode: OutputMode::default(),
+            stdout_maxbytes: 0,
+            stdout_backups: 0,
+            stderr_maxbytes: 0,
+            stderr_backups: 0,
+            output_format: String::new(),
+            output_time_format: String::new(),
+            output_buffer: 16 << 10,
+            max_line_length: 0,
+            log_rate_limit: None,
+            stdout_syslog: false,
+            stderr_syslog: false,
+            syslog: SyslogConfig::default(),
+            env: HashMap::new(),
+            clear_env: false,
+            env_passthrough: Vec::new(),
+            env_file: Vec::new(),
+            landlock: None,
+            nice: None,
+            ionice_class: None,
+            ionice_level: None,
+            cpu_affinity: Vec::new(),
+            oom_score_adj: None,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Default, PartialEq, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct LandlockConfig {
+    pub read_only: Vec<PathBuf>,
+    pub read_write: Vec<PathBuf>,
+}
+
+/// Budget of captured output per `interval` seconds, the lines over it are dropped
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[serde(deny_unknown_fields, default)]
+pub struct RateLimitConfig {
+    pub lines: u64,
+    #[serde(deserialize_with = "bytes_deserialize")]
+    pub bytes: u64,
+    pub interval: u64,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        Self {
+            lines: 0,
+            bytes: 0,
+            interval: 1,
+        }
+    }
+}
+
+/// How the output files are opened when the program is loaded, appending by default
+#[derive(Deserialize, Debug, Default, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputMode {
+    #[default]
+    Append,
+    Truncate,
+}
+
+/// `stdin: null` closes the input, `stdin: pipe` keeps the write end in taskmaster
+/// for the `send` instruction, any other value is a file to read from.
+/// Without the option the child inherits taskmaster's stdin.
+#[derive(Debug, Default, PartialEq, Clone)]
+pub enum StdinConfig {
+    #[default]
+    Inherit,
+    Null,
+    Pipe,
+    File(PathBuf),
+}
+
+impl<'de> Deserialize<'de> for StdinConfig {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error> where D: Deserializer<'de> {
+        match Option::<String>::deserialize(deserializer)?.as_deref() {
+            None | Some("null") => Ok(StdinConfig::Null),
+            Some("pipe") => Ok(StdinConfig::Pipe),
+            Some(path) => Ok(StdinConfig::File(PathBuf::from(path))),
+        }
+    }
 }
 
 fn umask_deserialize<'de, D>(deserializer: D) -> Result<u32, D::Error> where D: Deserializer<'de> {
@@ -34,13 +202,47 @@ fn umask_deserialize<'de, D>(deserializer: D) -> Result<u32, D::Error> where D:
     u32::from_str_radix(&buf.parse::<String>().map_err(serde::de::Error::custom)?, 8).map_err(serde::de::Error::custom)
 }
 
-fn workingdir_deserialize<'de, D>(deserializer: D) -> Result<PathBuf, D::Error> where D: Deserializer<'de> {
-    let buf = PathBuf::deserialize(deserializer)?;
+/// Size in bytes, either a number or a string with a `KB`, `MB` or `GB` suffix
+fn bytes_deserialize<'de, D>(deserializer: D) -> Result<u64, D::Error> where D: Deserializer<'de> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Bytes {
+        Number(u64),
+        Text(String),
+    }
+
+    match Bytes::deserialize(deserializer)? {
+        Bytes::Number(bytes) => Ok(bytes),
+        Bytes::Text(text) => {
+            let text = text.trim();
+            let (number, unit) = match text.find(|c: char| !c.is_ascii_digit()) {
+                Some(index) => text.split_at(index),
+                None => (text, ""),
+            };
+            let unit = match unit.trim().to_uppercase().as_str() {
+                "" | "B" => 1,
+                "KB" => 1 << 10,
+                "MB" => 1 << 20,
+                "GB" => 1 << 30,
+                _ => return Err(serde::de::Error::custom(format!("Invalid size unit: {unit}, expected KB, MB or GB"))),
+            };
+            let number = number.parse::<u64>().map_err(serde::de::Error::custom)?;
+            number.checked_mul(unit).ok_or_else(|| serde::de::Error::custom(format!("Size too large: {text}")))
+        },
+    }
+}
+
+fn paths_deserialize<'de, D>(deserializer: D) -> Result<Vec<PathBuf>, D::Error> where D: Deserializer<'de> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Paths {
+        One(PathBuf),
+        Many(Vec<PathBuf>),
+    }
 
-    if !buf.is_dir() {
-        Err(format!("Invalid working directory: {}", buf.to_str().unwrap())).map_err(serde::de::Error::custom)
-    } else {
-        Ok(buf)
+    match Paths::deserialize(deserializer)? {
+        Paths::One(path) => Ok(vec![path]),
+        Paths::Many(paths) => Ok(paths),
     }
 }
 
@@ -49,25 +251,491 @@ fn autorestart_deserialize<'de, D>(deserializer: D) -> Result<String, D::Error>
 
     match buf.as_str() {
         "always" | "never" | "unexpected" => Ok(buf),
-        _ => Err("Invalid autostart parameter: always, never, unexpected").map_err(serde::de::Error::custom)
+        _ => Err(serde::de::Error::custom("Invalid autostart parameter: always, never, unexpected"))
+    }
+}
+
+impl Config {
+    /// Deserializes the settings of a program, naming the first invalid one in the error
+    pub fn from_value(value: Value) -> Result<Config, Box<dyn Error>> {
+        serde_yaml::from_value(value.to_owned()).map_err(|err| {
+            let invalid = value.as_mapping().into_iter().flatten().find(|(key, value)| {
+                let setting = Mapping::from_iter([((*key).to_owned(), (*value).to_owned())]);
+                serde_yaml::from_value::<Config>(Value::Mapping(setting)).is_err()
+            });
+            match invalid.and_then(|(key, _)| key.as_str()) {
+                Some(key) => format!("{key}: {err}").into(),
+                None => err.into(),
+            }
+        })
+    }
+
+    /// Expands the variables of the values that support interpolation
+    pub fn interpolate(&mut self, variables: &Variables) -> Result<(), SettingError> {
+        self.cmd = self.cmd.expand(variables).map_err(|err| SettingError::prefixed("cmd", err))?;
+        self.workingdir = variables.expand_path(&self.workingdir).map_err(|err| SettingError::prefixed("workingdir", err))?;
+        self.stdout = self.stdout.expand(variables).map_err(|err| SettingError::prefixed("stdout", err))?;
+        self.stderr = self.stderr.expand(variables).map_err(|err| SettingError::prefixed("stderr", err))?;
+        if let StdinConfig::File(path) = &mut self.stdin {
+            *path = variables.expand_path(path).map_err(|err| SettingError::prefixed("stdin", err))?;
+        }
+        for path in self.env_file.iter_mut() {
+            *path = variables.expand_path(path).map_err(|err| SettingError::prefixed("env_file", err))?;
+        }
+        for (key, value) in self.env.iter_mut() {
+            *value = variables.expand(value).map_err(|err| SettingError::new("env", format!("env {key}: {err}")))?;
+        }
+        self.syslog.tag = variables.expand(&self.syslog.tag).map_err(|err| SettingError::new("syslog", format!("syslog tag: {err}")))?;
+        Ok(())
+    }
+
+    pub fn scheduling(&self) -> Result<Scheduling, Box<dyn Error>> {
+        Scheduling::new(self.nice, self.ionice(), self.cpu_affinity.clone(), self.oom_score_adj)
+    }
+
+    /// Without a class the io priority level is best-effort, without a level it's the default 4
+    fn ionice(&self) -> Option<(IoniceClass, u32)> {
+        match (self.ionice_class, self.ionice_level) {
+            (Some(class), level) => Some((class, level.unwrap_or(4))),
+            (None, Some(level)) => Some((IoniceClass::BestEffort, level)),
+            (None, None) => None,
+        }
+    }
+
+    /// Compares the configs, ignoring the scheduling settings that can be changed on running processes
+    pub fn eq_except_scheduling(&self, other: &Config) -> bool {
+        let (mut this, mut other) = (self.clone(), other.clone());
+        for config in [&mut this, &mut other] {
+            config.nice = None;
+            config.ionice_class = None;
+            config.ionice_level = None;
+            config.cpu_affinity.clear();
+            config.oom_score_adj = None;
+        }
+        this == other
+    }
+
+    /// `AUTO` outputs are files of `childlogdir` named after the program, the instance and the stream
+    pub fn auto_output(&mut self, name: &str, childlogdir: &Path) -> Result<(), SettingError> {
+        let paths = self.stdout.paths_mut().into_iter().map(|path| ("stdout", path))
+            .chain(self.stderr.paths_mut().into_iter().map(|path| ("stderr", path)));
+        for (stream, path) in paths {
+            if path.as_os_str() != AUTO_OUTPUT {
+                continue;
+            }
+            // Created by the monitor, reading the config has no effect on the filesystem
+            let childlogdir = std::path::absolute(childlogdir)
+                .map_err(|err| SettingError::new(stream, format!("childlogdir = '{}' {err}", childlogdir.display())))?;
+            // The instance number is expanded with the rest of the instance config
+            *path = PathBuf::from(escape(&childlogdir.to_string_lossy())).join(format!("{}-%(process_num)d-{stream}.log", escape(name)));
+        }
+        Ok(())
+    }
+
+    /// Sinks of a stream, the path form gives a file, syslog with `<stream>_syslog` and the memory buffer
+    pub fn sinks(&self, stream: Stream) -> Vec<SinkConfig> {
+        let (output, mode, maxbytes, backups, syslog) = match stream {
+            Stream::Stdout => (&self.stdout, self.stdout_mode, self.stdout_maxbytes, self.stdout_backups, self.stdout_syslog),
+            Stream::Stderr => (&self.stderr, self.stderr_mode, self.stderr_maxbytes, self.stderr_backups, self.stderr_syslog),
+        };
+        let path = match output {
+            OutputConfig::Sinks(sinks) => return sinks.clone(),
+            OutputConfig::Path(path) => path,
+        };
+        let mut sinks = Vec::new();
+        if !path.as_os_str().is_empty() {
+            sinks.push(SinkConfig::File(FileSinkConfig {
+                file: path.to_owned(),
+                mode,
+                maxbytes,
+                backups,
+            }));
+        }
+        if syslog {
+            sinks.push(SinkConfig::Name(SinkName::Syslog));
+        }
+        if self.output_buffer > 0 {
+            sinks.push(SinkConfig::Name(SinkName::Memory));
+        }
+        sinks
+    }
+
+    /// Checks the settings that don't depend on the system, after the interpolation
+    pub fn validate(&self) -> Result<(), SettingError> {
+        // The scheduling settings are checked one at a time to name the invalid one
+        let ionice = if self.ionice_level.is_some() { "ionice_level" } else { "ionice_class" };
+        let scheduling = [
+            ("nice", Scheduling::new(self.nice, None, Vec::new(), None)),
+            (ionice, Scheduling::new(None, self.ionice(), Vec::new(), None)),
+            ("cpu_affinity", Scheduling::new(None, None, self.cpu_affinity.clone(), None)),
+            ("oom_score_adj", Scheduling::new(None, None, Vec::new(), self.oom_score_adj)),
+        ];
+        for (key, result) in scheduling {
+            result.map_err(|err| SettingError::new(key, err))?;
+        }
+        LineFormat::new(&self.output_format, &self.output_time_format).map_err(|err| SettingError::new("output_format", err))?;
+        if self.log_rate_limit.as_ref().is_some_and(|limit| limit.interval == 0) {
+            Err(SettingError::new("log_rate_limit", "log_rate_limit interval must be at least 1 second"))?;
+        }
+        self.cmd.argv(self.shell).map_err(|err| SettingError::prefixed("cmd", err))?;
+        Ok(())
+    }
+
+    /// Checks the sinks, `programs` giving the config of the targets of the `program` sinks
+    pub fn validate_sinks<'a>(&self, programs: impl Fn(&str) -> Option<&'a Config>) -> Result<(), SettingError> {
+        for stream in [Stream::Stdout, Stream::Stderr] {
+            let key = stream.to_string();
+            for sink in self.sinks(stream) {
+                match sink {
+                    SinkConfig::Name(SinkName::Memory) if self.output_buffer == 0 => {
+                        Err(SettingError::new(&key, format!("{stream} memory sink with an output_buffer of 0")))?;
+                    },
+                    SinkConfig::Program(sink) => match programs(&sink.program) {
+                        Some(target) if target.stdin == StdinConfig::Pipe => (),
+                        Some(_) => Err(SettingError::new(&key, format!("{stream} sink program {} doesn't read its stdin from a pipe", sink.program)))?,
+                        None => Err(SettingError::new(&key, format!("{stream} sink program {} doesn't exist", sink.program)))?,
+                    },
+                    _ => (),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Config of the instance `process_num` of the program
+    pub fn instance(&self, process_num: usize) -> Result<Config, SettingError> {
+        let mut config = self.clone();
+        config.interpolate(&Variables::with_process_num(process_num))?;
+        if !config.workingdir.is_dir() {
+            Err(SettingError::new("workingdir", format!("Invalid working directory: {}", config.workingdir.display())))?;
+        }
+        Ok(config)
+    }
+}
+
+/// Error of a setting, with its `key` for `check` to locate it
+#[derive(Debug)]
+pub struct SettingError {
+    pub key: String,
+    message: String,
+}
+
+impl SettingError {
+    pub fn new(key: &str, message: impl fmt::Display) -> Self {
+        SettingError {
+            key: key.to_owned(),
+            message: message.to_string(),
+        }
+    }
+
+    /// Error whose message doesn't name the setting
+    pub fn prefixed(key: &str, err: impl fmt::Display) -> Self {
+        Self::new(key, format!("{key}: {err}"))
     }
 }
 
-#[derive(Deserialize)]
+impl fmt::Display for SettingError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl Error for SettingError {}
+
+/// Problem of a config file, with the program, template or section and the setting it's about
+/// when they are known
+#[derive(Debug, Clone, PartialEq)]
+pub struct Problem {
+    pub file: PathBuf,
+    pub section: Option<String>,
+    pub key: Option<String>,
+    pub message: String,
+}
+
+impl Problem {
+    pub fn new(file: &Path, section: Option<&str>, key: Option<&str>, message: impl fmt::Display) -> Self {
+        Problem {
+            file: file.to_owned(),
+            section: section.map(str::to_owned),
+            key: key.map(str::to_owned),
+            message: message.to_string(),
+        }
+    }
+
+    /// Adds the problem unless it's already there, the instances of a program share most of theirs
+    pub fn report(self, problems: &mut Vec<Problem>) {
+        if !problems.contains(&self) {
+            problems.push(self);
+        }
+    }
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.file.display(), self.message)
+    }
+}
+
+/// Settings, programs and config files read by `Parsing::parse`
+pub struct Parsed {
+    pub settings: Settings,
+    pub programs: HashMap<String, Program>,
+    /// Canonical paths of the files read, the main one first
+    pub files: Vec<PathBuf>,
+    /// File defining each program
+    pub origins: HashMap<String, PathBuf>,
+    /// Globs of the included files, from the directory of the file including them
+    pub includes: Vec<PathBuf>,
+}
+
+/// Config file: the `taskmaster` settings, the globs of the files to `include`, the `defaults`
+/// and `templates` of the programs, and the programs, either under `programs` or, as in older
+/// files, at the top level. The settings of the programs are read once the templates are applied.
+/// A program named like a section, such as `defaults` or `templates`, has to be under `programs`.
+#[derive(Default)]
 pub struct Parsing {
-    #[serde(flatten)]
-    pub tasks: HashMap<String, Config>,
+    settings: Settings,
+    templates: Templates,
+    /// Settings of each program before its templates are applied and the file defining it,
+    /// without settings when some are invalid
+    configs: Vec<(String, Option<Value>, PathBuf)>,
+    files: Vec<PathBuf>,
+    includes: Vec<PathBuf>,
 }
 
 impl Parsing {
-    pub fn parse(file_path: &PathBuf) -> Result<HashMap<String, Program>, Box<dyn Error>> {
-        let mut programs: HashMap<String, Program> = HashMap::new();
+    /// Settings of the file merged with the `overrides` of the command line, the programs
+    /// of the file and of the files it includes, failing on the first problem
+    pub fn parse(file_path: &Path, overrides: &Settings) -> Result<Parsed, Box<dyn Error>> {
+        let mut problems = Vec::new();
+        let parsed = Self::parse_all(file_path, overrides, &mut problems);
+        match problems.into_iter().next() {
+            Some(problem) => Err(problem.to_string().into()),
+            None => Ok(parsed),
+        }
+    }
+
+    /// Like `parse`, adding every problem found to `problems` and leaving out the programs they concern
+    pub fn parse_all(file_path: &Path, overrides: &Settings, problems: &mut Vec<Problem>) -> Parsed {
+        let mut parsing = Parsing::default();
+        parsing.collect(file_path, true, problems);
+        // Problems of the command line options are reported on the main file
+        let main = parsing.files.first().cloned().unwrap_or_else(|| file_path.to_owned());
+        let settings = std::mem::take(&mut parsing.settings).merge(overrides);
+        if let Err(err) = settings.validate() {
+            Problem::new(&main, Some("taskmaster"), Some(&err.key), format!("taskmaster: {err}")).report(problems);
+        }
+        let childlogdir = settings.childlogdir();
+        let host_node_name = Libc::gethostname().unwrap_or_else(|err| {
+            Problem::new(&main, None, None, format!("hostname: {err}")).report(problems);
+            String::new()
+        });
+
+        // Programs whose settings are read, and whether they have no problem
+        let mut configs: Vec<(String, Config, PathBuf, bool)> = Vec::new();
+        for (name, config, origin) in std::mem::take(&mut parsing.configs) {
+            // The invalid settings are reported where they are defined
+            let Some(config) = config else {
+                continue;
+            };
+            let problem = |key: Option<&str>, err: &dyn fmt::Display| Problem::new(&origin, Some(&name), key, format!("{name}: {err}"));
+            let mut config = match parsing.templates.resolve(config).and_then(Config::from_value) {
+                Ok(config) => config,
+                Err(err) => {
+                    problem(Some("extends"), &err).report(problems);
+                    continue;
+                },
+            };
+            let variables = Variables {
+                program_name: name.to_owned(),
+                here: origin.parent().map(|dir| dir.display().to_string()).unwrap_or_default(),
+                host_node_name: host_node_name.to_owned(),
+                process_num: None,
+            };
+            if let Err(err) = config.interpolate(&variables) {
+                problem(Some(&err.key), &err).report(problems);
+                continue;
+            }
+            let mut errors = Vec::new();
+            errors.extend(config.auto_output(&name, &childlogdir).err());
+            errors.extend(config.validate().err());
+            errors.extend((0..config.numprocs.max(1)).filter_map(|num| config.instance(num).err()));
+            let valid = errors.is_empty();
+            errors.into_iter().for_each(|err| problem(Some(&err.key), &err).report(problems));
+            configs.push((name, config, origin, valid));
+        }
+        let targets = |target: &str| configs.iter().find(|(name, ..)| name == target).map(|(_, config, ..)| config);
+        let invalid_sinks: Vec<String> = configs.iter().filter_map(|(name, config, origin, _)| {
+            let err = config.validate_sinks(targets).err()?;
+            Problem::new(origin, Some(name), Some(&err.key), format!("{name}: {err}")).report(problems);
+            Some(name.to_owned())
+        }).collect();
+
+        let mut programs = HashMap::new();
+        let mut origins = HashMap::new();
+        for (name, config, origin, _) in configs.into_iter().filter(|(name, .., valid)| *valid && !invalid_sinks.contains(name)) {
+            programs.insert(name.to_owned(), Program::new(&name, config, true));
+            origins.insert(name, origin);
+        }
+        Parsed {
+            settings,
+            programs,
+            files: parsing.files,
+            origins,
+            includes: parsing.includes,
+        }
+    }
+
+    /// Reads a YAML, TOML or supervisord file, see `Format::detect`
+    fn read(file_path: &Path) -> Result<Mapping, Box<dyn Error>> {
         let file_content = fs::read_to_string(file_path)?;
-        let mut parsed: Parsing = serde_yaml::from_str(&file_content)?;
+        let document: Value = match Format::detect(file_path, &file_content) {
+            Format::Yaml => serde_yaml::from_str(&file_content)?,
+            Format::Toml => toml::from_str(&file_content)?,
+            Format::Ini => ini::convert(&file_content)?,
+        };
+        match document {
+            Value::Mapping(document) => Ok(document),
+            Value::Null => Ok(Mapping::new()),
+            _ => Err("expected a mapping of programs".into()),
+        }
+    }
 
-        for (name, config) in parsed.tasks.drain() {
-            programs.insert(name, Program::new(config, None, true));
+    /// Gathers the programs and templates of the file and of its includes, with the canonical path
+    /// of the file each one comes from. The globs are relative to the directory of the file including them.
+    fn collect(&mut self, file_path: &Path, main: bool, problems: &mut Vec<Problem>) {
+        let file_path = match file_path.canonicalize() {
+            Ok(file_path) => file_path,
+            Err(err) => return Problem::new(file_path, None, None, err).report(problems),
+        };
+        // A file included twice, or including itself, is only read once
+        if self.files.contains(&file_path) {
+            return;
+        }
+        self.files.push(file_path.to_owned());
+        let document = match Self::read(&file_path) {
+            Ok(document) => document,
+            Err(err) => return Problem::new(&file_path, None, None, err).report(problems),
+        };
+        let problem = |section: &str, message: &str| Problem::new(&file_path, Some(section), None, message);
+        let mut include = Vec::new();
+        for (key, value) in document {
+            match key.as_str() {
+                // A program of an older file, a template can't be named `cmd` either
+                Some(section @ ("defaults" | "templates")) if value.get("cmd").is_some() => {
+                    problem(section, &format!("{section} is a reserved name, a program named {section} has to be under programs")).report(problems);
+                },
+                Some("taskmaster") if main => {
+                    let (fields, _) = fields::<Settings>(&file_path, "taskmaster", value, problems);
+                    match serde_yaml::from_value(Value::Mapping(fields)) {
+                        Ok(settings) => self.settings = settings,
+                        Err(err) => problem("taskmaster", &format!("taskmaster: {err}")).report(problems),
+                    }
+                },
+                Some("defaults") if main => {
+                    let (fields, _) = fields::<Config>(&file_path, "defaults", value, problems);
+                    // Only fails when the value isn't a mapping
+                    _ = self.templates.set_defaults(Value::Mapping(fields));
+                },
+                Some(section @ ("taskmaster" | "defaults")) => {
+                    problem(section, &format!("the {section} section is only read from the main config file")).report(problems);
+                },
+                Some("templates") => match value {
+                    Value::Mapping(templates) => templates.into_iter().for_each(|(name, template)| self.template(&file_path, name, template, problems)),
+                    Value::Null => (),
+                    _ => problem("templates", "templates: expected a mapping of templates").report(problems),
+                },
+                Some("include") => match serde_yaml::from_value::<Vec<PathBuf>>(value) {
+                    Ok(patterns) => include = patterns,
+                    Err(err) => problem("include", &format!("include: {err}")).report(problems),
+                },
+                Some("programs") => match value {
+                    Value::Mapping(programs) => programs.into_iter().for_each(|(name, config)| self.program(&file_path, name, config, problems)),
+                    Value::Null => (),
+                    _ => problem("programs", "programs: expected a mapping of programs").report(problems),
+                },
+                _ => self.program(&file_path, key, value, problems),
+            }
+        }
+        let dir = file_path.parent().unwrap_or(Path::new("/"));
+        for pattern in include {
+            let pattern = dir.join(pattern);
+            if !self.includes.contains(&pattern) {
+                self.includes.push(pattern.to_owned());
+            }
+            for path in glob::expand(&pattern) {
+                self.collect(&path, false, problems);
+            }
         }
-        Ok(programs)
     }
-}
\ No newline at end of file
+
+    fn template(&mut self, file: &Path, name: Value, template: Value, problems: &mut Vec<Problem>) {
+        let Value::String(name) = name else {
+            return Problem::new(file, Some("templates"), None, format!("invalid template name {name:?}")).report(problems);
+        };
+        // Added even with invalid settings, which are left out, for the programs extending it
+        let (fields, _) = fields::<Config>(file, &name, without_extends(&template), problems);
+        let template = with_extends(fields, &template);
+        if let Err(err) = self.templates.add(name.to_owned(), template, file) {
+            Problem::new(file, Some(&name), None, err).report(problems);
+        }
+    }
+
+    fn program(&mut self, file: &Path, name: Value, config: Value, problems: &mut Vec<Problem>) {
+        let Value::String(name) = name else {
+            return Problem::new(file, None, None, format!("invalid program name {name:?}")).report(problems);
+        };
+        if let Some((_, _, other)) = self.configs.iter().find(|(other, ..)| *other == name) {
+            let message = format!("program {name} is also defined in '{}'", other.display());
+            return Problem::new(file, Some(&name), None, message).report(problems);
+        }
+        // A partly valid program would report problems of the missing settings
+        let config = match fields::<Config>(file, &name, without_extends(&config), problems) {
+            (fields, true) => Some(with_extends(fields, &config)),
+            (_, false) => None,
+        };
+        self.configs.push((name, config, file.to_owned()));
+    }
+}
+
+/// Checks the settings of `section` one at a time to report every invalid one. Returns the
+/// valid ones, and whether they all were.
+fn fields<T: DeserializeOwned>(file: &Path, section: &str, value: Value, problems: &mut Vec<Problem>) -> (Mapping, bool) {
+    let fields = match value {
+        Value::Mapping(fields) => fields,
+        Value::Null => Mapping::new(),
+        _ => {
+            Problem::new(file, Some(section), None, format!("{section}: expected a mapping")).report(problems);
+            return (Mapping::new(), false);
+        },
+    };
+    let mut valid = Mapping::new();
+    for (key, value) in fields.iter() {
+        let field = Mapping::from_iter([(key.to_owned(), value.to_owned())]);
+        match serde_yaml::from_value::<T>(Value::Mapping(field)) {
+            Ok(_) => _ = valid.insert(key.to_owned(), value.to_owned()),
+            Err(err) => {
+                let key = key.as_str().unwrap_or_default();
+                Problem::new(file, Some(section), Some(key), format!("{section}: {key}: {err}")).report(problems);
+            },
+        }
+    }
+    let all_valid = valid.len() == fields.len();
+    (valid, all_valid)
+}
+
+// `extends` is the only setting of a template or a program that isn't a setting of `Config`
+fn without_extends(value: &Value) -> Value {
+    let mut value = value.to_owned();
+    if let Value::Mapping(settings) = &mut value {
+        settings.remove(EXTENDS);
+    }
+    value
+}
+
+fn with_extends(mut fields: Mapping, value: &Value) -> Value {
+    if let Some(extends) = value.get(EXTENDS) {
+        fields.insert(Value::from(EXTENDS), extends.to_owned());
+    }
+    Value::Mapping(fields)
+}
@@ -0,0 +1,182 @@
+use std::{collections::HashMap, env, fmt, fs, path::{Path, PathBuf}};
+
+use crate::monitor::output::Stream;
+use crate::sys::{self, Libc};
+
+use super::{AUTO_OUTPUT, Config, Parsing, Problem, dotenv, ini};
+use super::settings::Settings;
+use super::sink::SinkConfig;
+
+/// Problem found in a config file, with the line and column of the setting when they are known
+#[derive(Debug, Clone, PartialEq)]
+pub struct Located {
+    pub problem: Problem,
+    pub location: Option<(usize, usize)>,
+}
+
+impl fmt::Display for Located {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.location {
+            Some((line, column)) => write!(f, "{}:{line}:{column}: {}", self.problem.file.display(), self.problem.message),
+            None => write!(f, "{}: {}", self.problem.file.display(), self.problem.message),
+        }
+    }
+}
+
+/// Reads the config file and its includes like `Parsing::parse`, without starting anything,
+/// and returns every problem found instead of stopping at the first one. The files the
+/// programs and taskmaster would use are checked too.
+pub fn check(file_path: &Path, overrides: &Settings) -> Vec<Located> {
+    let mut problems = Vec::new();
+    let parsed = Parsing::parse_all(file_path, overrides, &mut problems);
+    if let Some(main) = parsed.files.first() {
+        settings(main, &parsed.settings, &mut problems);
+    }
+    let childlogdir = parsed.settings.childlogdir();
+    let mut names: Vec<&String> = parsed.programs.keys().collect();
+    names.sort();
+    for name in names {
+        let config = &parsed.programs[name].config;
+        for num in 0..config.numprocs.max(1) {
+            instance(&parsed.origins[name], name, config, num, &childlogdir, &mut problems);
+        }
+    }
+    let mut sources: HashMap<PathBuf, String> = HashMap::new();
+    let mut located: Vec<Located> = problems.into_iter().map(|problem| {
+        let content = sources.entry(problem.file.to_owned()).or_insert_with(|| fs::read_to_string(&problem.file).unwrap_or_default());
+        let location = problem.section.as_deref().and_then(|section| locate(content, section, problem.key.as_deref()));
+        Located {
+            problem,
+            location,
+        }
+    }).collect();
+    // In the order of the files and of the lines
+    located.sort_by_key(|located| (parsed.files.iter().position(|file| *file == located.problem.file), located.location));
+    located
+}
+
+/// Line and column of `key` in the section or program `section`, or of `section` itself
+fn locate(content: &str, section: &str, key: Option<&str>) -> Option<(usize, usize)> {
+    let lines: Vec<&str> = content.lines().collect();
+    let headers = [format!("[{section}]"), format!("[program:{section}]"), format!("[programs.{section}]"), format!("[templates.{section}]")];
+    // A program named like a setting is found at the lowest indentation
+    let (start, indent) = lines.iter().enumerate()
+        .filter(|(_, line)| headers.iter().any(|header| line.trim() == header) || is_key(line.trim(), section))
+        .map(|(index, line)| (index, indentation(line)))
+        .min_by_key(|&(_, indent)| indent)?;
+    let keys = match key {
+        Some(key) => [Some(key), ini::supervisord_key(key)],
+        None => return Some((start + 1, indent + 1)),
+    };
+    let is_header = lines[start].trim_start().starts_with('[');
+    for (index, line) in lines.iter().enumerate().skip(start + 1) {
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with(['#', ';']) {
+            continue;
+        }
+        if trimmed.starts_with('[') || (!is_header && indentation(line) <= indent) {
+            break;
+        }
+        if keys.iter().flatten().any(|key| is_key(trimmed, key)) {
+            return Some((index + 1, indentation(line) + 1));
+        }
+    }
+    Some((start + 1, indent + 1))
+}
+
+fn indentation(line: &str) -> usize {
+    line.len() - line.trim_start().len()
+}
+
+// `key:` in YAML and `key =` in TOML and INI, the key may be quoted
+fn is_key(line: &str, key: &str) -> bool {
+    line.trim_start_matches(['"', '\''])
+        .strip_prefix(key)
+        .map(|rest| rest.trim_start_matches(['"', '\'']).trim_start())
+        .is_some_and(|rest| rest.starts_with([':', '=']))
+}
+
+// Problems of the command line options are reported on the main file
+fn settings(main: &Path, settings: &Settings, problems: &mut Vec<Problem>) {
+    let mut problem = |key: &str, err: String| Problem::new(main, Some("taskmaster"), Some(key), format!("taskmaster: {key}: {err}")).report(problems);
+    let logger = settings.logger();
+    if logger.syslog.is_none() {
+        if let Err(err) = writable(&logger.path) {
+            problem("log_file", err);
+        }
+    }
+    if let Some(Err(err)) = settings.pidfile.as_ref().map(|pidfile| writable(pidfile)) {
+        problem("pidfile", err);
+    }
+    if let Err(err) = writable(&settings.childlogdir()) {
+        problem("childlogdir", err);
+    }
+}
+
+// The files of the instance, its settings were checked by `Parsing::parse_all`
+fn instance(origin: &Path, name: &str, config: &Config, num: usize, childlogdir: &Path, problems: &mut Vec<Problem>) {
+    let mut problem = |key: &str, err: String| Problem::new(origin, Some(name), Some(key), format!("{name}: {err}")).report(problems);
+    // The settings report if the childlogdir of the `AUTO` outputs can't be created
+    let created = std::path::absolute(childlogdir).ok().filter(|_| !childlogdir.is_dir());
+    let Ok(config) = config.instance(num) else {
+        return;
+    };
+    if let Ok(argv) = config.cmd.argv(config.shell) {
+        if let Err(err) = executable(&argv[0], &config) {
+            problem("cmd", format!("cmd: {err}"));
+        }
+    }
+    for stream in [Stream::Stdout, Stream::Stderr] {
+        for sink in config.sinks(stream) {
+            match sink {
+                SinkConfig::File(file) if file.file != Path::new(AUTO_OUTPUT) && file.file.parent() != created.as_deref() => {
+                    if let Err(err) = writable(&config.workingdir.join(&file.file)) {
+                        problem(&stream.to_string(), format!("{stream}: {err}"));
+                    }
+                },
+                _ => (),
+            }
+        }
+    }
+    for path in &config.env_file {
+        if let Err(err) = dotenv::read(&config.workingdir.join(path)) {
+            problem("env_file", err.to_string());
+        }
+    }
+}
+
+/// Whether taskmaster can write the file, or create it in its directory
+fn writable(path: &Path) -> Result<(), String> {
+    if path.exists() {
+        return match Libc::access(path, sys::W_OK) {
+            true => Ok(()),
+            false => Err(format!("'{}' is not writable", path.display())),
+        };
+    }
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    match dir.is_dir() {
+        true if Libc::access(dir, sys::W_OK) => Ok(()),
+        true => Err(format!("directory '{}' is not writable", dir.display())),
+        false => Err(format!("directory '{}' doesn't exist", dir.display())),
+    }
+}
+
+/// Looks for the program like the shell, in `workingdir` if it has a `/` or else in the `PATH`
+fn executable(program: &str, config: &Config) -> Result<(), String> {
+    let candidates: Vec<PathBuf> = match program.contains('/') {
+        true => vec![config.workingdir.join(program)],
+        false => {
+            let path = config.env.get("PATH").cloned().or_else(|| env::var("PATH").ok()).unwrap_or_default();
+            env::split_paths(&path).map(|dir| dir.join(program)).collect()
+        },
+    };
+    match candidates.iter().find(|path| path.is_file()) {
+        Some(path) if Libc::access(path, sys::X_OK) => Ok(()),
+        Some(path) => Err(format!("'{}' is not executable", path.display())),
+        None if program.contains('/') => Err(format!("'{}' doesn't exist", candidates[0].display())),
+        None => Err(format!("'{program}' isn't in the PATH")),
+    }
+}
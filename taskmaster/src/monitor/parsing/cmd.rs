@@ -0,0 +1,110 @@
+use std::{error::Error, fmt};
+use serde::Deserialize;
+
+use super::interpolation::Variables;
+
+const SHELL: &str = "/bin/sh";
+
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[serde(untagged)]
+pub enum Cmd {
+    Line(String),
+    Args(Vec<String>),
+}
+
+impl Default for Cmd {
+    fn default() -> Self {
+        Cmd::Line(String::new())
+    }
+}
+
+impl fmt::Display for Cmd {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Cmd::Line(line) => write!(f, "{line}"),
+            Cmd::Args(args) => write!(f, "{args:?}"),
+        }
+    }
+}
+
+impl Cmd {
+    /// With `shell` the line is handed to `/bin/sh -c`, in the list form the first element
+    /// is the script and the following ones become its positional parameters, from `$1`
+    /// as `$0` is set to the shell.
+    pub fn argv(&self, shell: bool) -> Result<Vec<String>, Box<dyn Error>> {
+        let argv = match (self, shell) {
+            (Cmd::Line(line), false) => Self::split(line)?,
+            (Cmd::Line(line), true) => vec![SHELL.to_owned(), "-c".to_owned(), line.to_owned()],
+            (Cmd::Args(args), false) => args.to_owned(),
+            (Cmd::Args(args), true) => match args.split_first() {
+                Some((script, args)) => [SHELL, "-c", script, SHELL].into_iter().map(str::to_owned).chain(args.iter().cloned()).collect(),
+                None => Vec::new(),
+            },
+        };
+        match argv.first() {
+            Some(program) if !program.is_empty() => Ok(argv),
+            _ => Err("Missing program name".into()),
+        }
+    }
+
+    pub fn expand(&self, variables: &Variables) -> Result<Cmd, Box<dyn Error>> {
+        match self {
+            Cmd::Line(line) => Ok(Cmd::Line(variables.expand(line)?)),
+            Cmd::Args(args) => Ok(Cmd::Args(args.iter().map(|arg| variables.expand(arg)).collect::<Result<_, _>>()?)),
+        }
+    }
+
+    /// Splits a command line into words following the POSIX shell quoting rules,
+    /// without any expansion.
+    pub fn split(line: &str) -> Result<Vec<String>, Box<dyn Error>> {
+        let mut words = Vec::new();
+        let mut word: Option<String> = None;
+        let mut chars = line.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                ' ' | '\t' | '\n' => {
+                    if let Some(word) = word.take() {
+                        words.push(word);
+                    }
+                },
+                '\\' => match chars.next() {
+                    Some('\n') => {},
+                    Some(escaped) => word.get_or_insert_with(String::new).push(escaped),
+                    None => Err("trailing backslash")?,
+                },
+                '\'' => {
+                    let word = word.get_or_insert_with(String::new);
+                    loop {
+                        match chars.next() {
+                            Some('\'') => break,
+                            Some(c) => word.push(c),
+                            None => Err("unterminated single quote")?,
+                        }
+                    }
+                },
+                '"' => {
+                    let word = word.get_or_insert_with(String::new);
+                    loop {
+                        match chars.next() {
+                            Some('"') => break,
+                            Some('\\') => match chars.next() {
+                                Some('\n') => {},
+                                Some(c @ ('$' | '`' | '"' | '\\')) => word.push(c),
+                                Some(c) => { word.push('\\'); word.push(c); },
+                                None => Err("unterminated double quote")?,
+                            },
+                            Some(c) => word.push(c),
+                            None => Err("unterminated double quote")?,
+                        }
+                    }
+                },
+                c => word.get_or_insert_with(String::new).push(c),
+            }
+        }
+        if let Some(word) = word {
+            words.push(word);
+        }
+        Ok(words)
+    }
+}
@@ -0,0 +1,57 @@
+use std::{collections::BTreeMap, error::Error, fs, path::Path};
+
+/// Reads a dotenv file: `KEY=value` lines, optionally prefixed with `export`,
+/// with `#` comments, single quoted literal values and double quoted values
+/// supporting `\n`, `\t`, `\"` and `\\` escapes.
+pub fn read(path: &Path) -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+    let content = fs::read_to_string(path).map_err(|err| format!("env_file = '{}' {}", path.display(), err))?;
+    let mut variables = BTreeMap::new();
+
+    for (index, line) in content.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let (key, value) = parse_line(line).map_err(|err| format!("{}:{}: {err}", path.display(), index + 1))?;
+        variables.insert(key, value);
+    }
+    Ok(variables)
+}
+
+fn parse_line(line: &str) -> Result<(String, String), Box<dyn Error>> {
+    let line = line.strip_prefix("export ").unwrap_or(line);
+    let (key, value) = line.split_once('=').ok_or("expected KEY=value")?;
+    let key = key.trim();
+    if key.is_empty() || key.starts_with(|c: char| c.is_ascii_digit())
+        || !key.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        Err(format!("invalid variable name '{key}'"))?;
+    }
+    let value = value.trim_start();
+    let value = if let Some(quoted) = value.strip_prefix('\'') {
+        quoted.split_once('\'').ok_or("unterminated single quote")?.0.to_owned()
+    } else if let Some(quoted) = value.strip_prefix('"') {
+        let mut unquoted = String::new();
+        let mut chars = quoted.chars();
+        loop {
+            match chars.next() {
+                Some('"') => break,
+                Some('\\') => match chars.next() {
+                    Some('n') => unquoted.push('\n'),
+                    Some('t') => unquoted.push('\t'),
+                    Some(c @ ('"' | '\\' | '$')) => unquoted.push(c),
+                    Some(c) => { unquoted.push('\\'); unquoted.push(c); },
+                    None => Err("unterminated double quote")?,
+                },
+                Some(c) => unquoted.push(c),
+                None => Err("unterminated double quote")?,
+            }
+        }
+        unquoted
+    } else {
+        match value.find(" #") {
+            Some(comment) => value[..comment].trim_end().to_owned(),
+            None => value.trim_end().to_owned(),
+        }
+    };
+    Ok((key.to_owned(), value))
+}
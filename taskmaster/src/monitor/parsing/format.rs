@@ -0,0 +1,41 @@
+use std::path::Path;
+
+// Sections of a supervisord file without a `type:` prefix
+const SUPERVISORD_SECTIONS: [&str; 5] = ["supervisord", "supervisorctl", "unix_http_server", "inet_http_server", "include"];
+
+/// Syntax of a config file
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Yaml,
+    Toml,
+    Ini,
+}
+
+impl Format {
+    /// From the extension, or from the content for `.conf` and other extensions:
+    /// `[program:x]` sections are supervisord's, other sections and `key = value` are TOML.
+    pub fn detect(path: &Path, content: &str) -> Format {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("yaml" | "yml") => return Format::Yaml,
+            Some("toml") => return Format::Toml,
+            Some("ini") => return Format::Ini,
+            _ => (),
+        }
+        let line = content.lines().map(str::trim).find(|line| !line.is_empty() && !line.starts_with(['#', ';']));
+        match line {
+            Some(line) if line.starts_with('[') => {
+                let section = line.trim_start_matches('[').split(']').next().unwrap_or_default().trim();
+                match section.contains(':') || SUPERVISORD_SECTIONS.contains(&section) {
+                    true => Format::Ini,
+                    false => Format::Toml,
+                }
+            },
+            Some(line) => match (line.find('='), line.find(':')) {
+                (Some(equal), Some(colon)) if equal < colon => Format::Toml,
+                (Some(_), None) => Format::Toml,
+                _ => Format::Yaml,
+            },
+            None => Format::Yaml,
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use std::{fs, path::{Path, PathBuf}};
+
+/// Existing paths matching `pattern`, sorted. In each component `*` matches any characters,
+/// `?` one character and `[abc]`, `[a-z]` or `[!abc]` one character of a set.
+/// Names starting with a dot are only matched by a component starting with a dot.
+/// A pattern without wildcard is returned as is, whether it exists or not.
+pub fn expand(pattern: &Path) -> Vec<PathBuf> {
+    let mut paths = vec![PathBuf::new()];
+    let mut wildcard = false;
+    for component in pattern.components() {
+        let component_pattern: Vec<char> = component.as_os_str().to_string_lossy().chars().collect();
+        if !component_pattern.iter().any(|c| matches!(c, '*' | '?' | '[')) {
+            paths.iter_mut().for_each(|path| path.push(component));
+            continue;
+        }
+        wildcard = true;
+        let mut matched = Vec::new();
+        for path in paths {
+            let dir = if path.as_os_str().is_empty() { Path::new(".") } else { &path };
+            let entries = match fs::read_dir(dir) {
+                Ok(entries) => entries,
+                Err(_) => continue,
+            };
+            for entry in entries.flatten() {
+                if component_matches(&component_pattern, &entry.file_name().to_string_lossy()) {
+                    matched.push(path.join(entry.file_name()));
+                }
+            }
+        }
+        matched.sort();
+        paths = matched;
+    }
+    if wildcard {
+        paths.retain(|path| path.exists());
+    }
+    paths
+}
+
+/// Whether `path` is one of the paths `pattern` expands to when it exists
+pub fn is_match(pattern: &Path, path: &Path) -> bool {
+    let (mut patterns, mut components) = (pattern.components(), path.components());
+    loop {
+        match (patterns.next(), components.next()) {
+            (None, None) => return true,
+            (Some(pattern), Some(component)) => {
+                let pattern: Vec<char> = pattern.as_os_str().to_string_lossy().chars().collect();
+                if !component_matches(&pattern, &component.as_os_str().to_string_lossy()) {
+                    return false;
+                }
+            },
+            _ => return false,
+        }
+    }
+}
+
+fn component_matches(pattern: &[char], name: &str) -> bool {
+    let name: Vec<char> = name.chars().collect();
+    if name.first() == Some(&'.') && pattern.first() != Some(&'.') {
+        return false;
+    }
+    matches(pattern, &name)
+}
+
+fn matches(pattern: &[char], name: &[char]) -> bool {
+    match pattern.first() {
+        None => name.is_empty(),
+        Some('*') => (0..=name.len()).any(|index| matches(&pattern[1..], &name[index..])),
+        Some('?') => !name.is_empty() && matches(&pattern[1..], &name[1..]),
+        Some('[') => match pattern.iter().skip(2).position(|&c| c == ']').map(|index| index + 2) {
+            Some(end) => name.first().is_some_and(|&c| in_set(&pattern[1..end], c)) && matches(&pattern[end + 1..], &name[1..]),
+            None => name.first() == Some(&'[') && matches(&pattern[1..], &name[1..]),
+        },
+        Some(c) => name.first() == Some(c) && matches(&pattern[1..], &name[1..]),
+    }
+}
+
+fn in_set(set: &[char], c: char) -> bool {
+    let (negated, set) = match set.first() {
+        Some('!') => (true, &set[1..]),
+        _ => (false, set),
+    };
+    let mut found = false;
+    let mut index = 0;
+    while index < set.len() {
+        if index + 2 < set.len() && set[index + 1] == '-' {
+            found |= (set[index]..=set[index + 2]).contains(&c);
+            index += 3;
+        } else {
+            found |= set[index] == c;
+            index += 1;
+        }
+    }
+    found != negated
+}
@@ -0,0 +1,228 @@
+use std::error::Error;
+use serde_yaml::{Mapping, Value};
+
+// Settings of supervisord without an equivalent, skipped so that existing files load
+const IGNORED_KEYS: [&str; 10] = [
+    "process_name", "numprocs_start", "priority", "stopasgroup", "killasgroup", "serverurl",
+    "stdout_capture_maxbytes", "stdout_events_enabled", "stderr_capture_maxbytes", "stderr_events_enabled",
+];
+
+// Name of the section and its keys with their values
+type Section = (String, Vec<(String, String)>);
+
+/// Converts a supervisord file to the document read from YAML. `[program:x]` sections are
+/// the programs with supervisord's keys renamed and supervisord's defaults where they differ from taskmaster's
+/// (started, `AUTO` logfiles rotated at 50MB with 10 backups), `[group:x]` sets the `group` of its `programs`,
+/// `[supervisord]` gives the `taskmaster` settings and `[include]` the `files` to include.
+/// The other sections are ignored.
+pub fn convert(content: &str) -> Result<Value, Box<dyn Error>> {
+    let mut document = Mapping::new();
+    let mut settings = Mapping::new();
+    let mut programs = Mapping::new();
+    let mut groups = Vec::new();
+    for (section, keys) in sections(content)? {
+        match section.split_once(':') {
+            Some(("program", name)) => {
+                let mut config = Mapping::new();
+                for (key, value) in keys {
+                    program_key(&mut config, &key, &value).map_err(|err| format!("[{section}] {key}: {err}"))?;
+                }
+                // Defaults of supervisord that differ from taskmaster's, `NONE` still disables a log file
+                let defaults: [(&str, Value); 7] = [
+                    ("autostart", true.into()),
+                    ("stdout", "AUTO".into()),
+                    ("stdout_maxbytes", "50MB".into()),
+                    ("stdout_backups", 10.into()),
+                    ("stderr", "AUTO".into()),
+                    ("stderr_maxbytes", "50MB".into()),
+                    ("stderr_backups", 10.into()),
+                ];
+                for (key, value) in defaults {
+                    config.entry(key.into()).or_insert(value);
+                }
+                programs.insert(name.trim().into(), config.into());
+            },
+            Some(("group", name)) => {
+                let members = keys.into_iter().find(|(key, _)| key == "programs").map(|(_, members)| members).unwrap_or_default();
+                groups.push((name.trim().to_owned(), members));
+            },
+            Some((kind @ ("eventlistener" | "fcgi-program"), _)) => Err(format!("[{section}]: {kind} sections are not supported"))?,
+            Some(_) => (),
+            None if section == "supervisord" => {
+                for (key, value) in keys {
+                    match key.as_str() {
+                        "logfile" => settings.insert("log_file".into(), value.into()),
+                        "loglevel" => settings.insert("log_level".into(), log_level(&value).into()),
+                        "pidfile" | "childlogdir" => settings.insert(key.into(), value.into()),
+                        _ => None,
+                    };
+                }
+            },
+            None if section == "include" => {
+                let files = keys.into_iter().find(|(key, _)| key == "files").map(|(_, files)| files).unwrap_or_default();
+                let files: Vec<Value> = files.split_whitespace().map(Value::from).collect();
+                document.insert("include".into(), files.into());
+            },
+            None => (),
+        }
+    }
+    for (group, members) in groups {
+        for member in members.split(',').map(str::trim).filter(|member| !member.is_empty()) {
+            let config = programs.get_mut(member).and_then(Value::as_mapping_mut)
+                .ok_or_else(|| format!("[group:{group}] programs: program {member} doesn't exist"))?;
+            config.insert("group".into(), group.as_str().into());
+        }
+    }
+    if !settings.is_empty() {
+        document.insert("taskmaster".into(), settings.into());
+    }
+    document.insert("programs".into(), programs.into());
+    Ok(document.into())
+}
+
+/// Sections with their keys in order. Lines starting with whitespace continue the value
+/// of the previous key, `;` and `#` start comments and keys are separated by `=` or `:`.
+/// The separator is the one following the name of the key, the value may contain the other.
+fn sections(content: &str) -> Result<Vec<Section>, Box<dyn Error>> {
+    let mut sections: Vec<Section> = Vec::new();
+    for (index, line) in content.lines().enumerate() {
+        let line = strip_comment(line);
+        if line.trim().is_empty() {
+            continue;
+        }
+        let location = || format!("line {}", index + 1);
+        if line.starts_with(char::is_whitespace) {
+            let (_, value) = sections.last_mut().and_then(|(_, keys)| keys.last_mut())
+                .ok_or_else(|| format!("{}: continuation line without a key", location()))?;
+            value.push('\n');
+            value.push_str(line.trim());
+        } else if let Some(section) = line.trim().strip_prefix('[') {
+            let section = section.strip_suffix(']').ok_or_else(|| format!("{}: expected ']'", location()))?;
+            sections.push((section.trim().to_owned(), Vec::new()));
+        } else {
+            let (_, keys) = sections.last_mut().ok_or_else(|| format!("{}: key outside of a section", location()))?;
+            let line = line.trim_start();
+            let end = line.find(|c: char| !(c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'))).unwrap_or(line.len());
+            let (key, rest) = line.split_at(end);
+            let value = rest.trim_start().strip_prefix(['=', ':']).filter(|_| !key.is_empty())
+                .ok_or_else(|| format!("{}: expected key = value", location()))?;
+            keys.push((key.to_owned(), value.trim().to_owned()));
+        }
+    }
+    Ok(sections)
+}
+
+// Inline comments need a whitespace before the `;`
+fn strip_comment(line: &str) -> &str {
+    if line.trim_start().starts_with([';', '#']) {
+        return "";
+    }
+    match line.find(" ;").or_else(|| line.find("\t;")) {
+        Some(index) => &line[..index],
+        None => line,
+    }
+}
+
+fn program_key(config: &mut Mapping, key: &str, value: &str) -> Result<(), Box<dyn Error>> {
+    let (key, value): (&str, Value) = match key {
+        "command" => ("cmd", value.into()),
+        "directory" => ("workingdir", value.into()),
+        "umask" => ("umask", value.into()),
+        "startsecs" => ("starttime", scalar(value)),
+        "stopwaitsecs" => ("stoptime", scalar(value)),
+        "autostart" | "redirect_stderr" | "stdout_syslog" | "stderr_syslog" => (key, boolean(value)?.into()),
+        "autorestart" => match value.to_lowercase().as_str() {
+            "unexpected" => ("autorestart", "unexpected".into()),
+            value => ("autorestart", if boolean(value)? { "always" } else { "never" }.into()),
+        },
+        "exitcodes" => ("exitcodes", value.split(',').map(|code| scalar(code.trim())).collect::<Vec<_>>().into()),
+        "stopsignal" => {
+            let signal = value.to_uppercase();
+            ("stopsignal", if signal.starts_with("SIG") { signal } else { format!("SIG{signal}") }.into())
+        },
+        "stdout_logfile" | "stderr_logfile" => {
+            let stream = &key[..6];
+            (stream, if value == "NONE" { "" } else { value }.into())
+        },
+        "stdout_logfile_maxbytes" => ("stdout_maxbytes", value.into()),
+        "stderr_logfile_maxbytes" => ("stderr_maxbytes", value.into()),
+        "stdout_logfile_backups" => ("stdout_backups", scalar(value)),
+        "stderr_logfile_backups" => ("stderr_backups", scalar(value)),
+        "environment" => ("env", environment(value).into()),
+        "user" => Err("running programs as another user is not supported")?,
+        key if IGNORED_KEYS.contains(&key) => return Ok(()),
+        // Keys of taskmaster itself
+        key => (key, scalar(value)),
+    };
+    config.insert(key.into(), value);
+    Ok(())
+}
+
+/// Name in supervisord's files of a key of taskmaster, when it differs
+pub fn supervisord_key(key: &str) -> Option<&'static str> {
+    match key {
+        "cmd" => Some("command"),
+        "workingdir" => Some("directory"),
+        "starttime" => Some("startsecs"),
+        "stoptime" => Some("stopwaitsecs"),
+        "stdout" => Some("stdout_logfile"),
+        "stderr" => Some("stderr_logfile"),
+        "stdout_maxbytes" => Some("stdout_logfile_maxbytes"),
+        "stderr_maxbytes" => Some("stderr_logfile_maxbytes"),
+        "stdout_backups" => Some("stdout_logfile_backups"),
+        "stderr_backups" => Some("stderr_logfile_backups"),
+        "env" => Some("environment"),
+        "log_file" => Some("logfile"),
+        "log_level" => Some("loglevel"),
+        _ => None,
+    }
+}
+
+fn scalar(value: &str) -> Value {
+    match (value.parse::<i64>(), value) {
+        (Ok(number), _) => number.into(),
+        (_, "true") => true.into(),
+        (_, "false") => false.into(),
+        _ => value.into(),
+    }
+}
+
+fn boolean(value: &str) -> Result<bool, Box<dyn Error>> {
+    match value.to_lowercase().as_str() {
+        "true" | "yes" | "on" | "1" => Ok(true),
+        "false" | "no" | "off" | "0" => Ok(false),
+        _ => Err(format!("invalid boolean '{value}'").into()),
+    }
+}
+
+fn log_level(level: &str) -> &str {
+    match level {
+        "critical" => "error",
+        "trace" | "blather" => "debug",
+        level => level,
+    }
+}
+
+/// `KEY="value",KEY2=value2`, the values may be quoted to hold commas
+fn environment(value: &str) -> Mapping {
+    let mut variables = Mapping::new();
+    let mut chars = value.chars().peekable();
+    loop {
+        let key: String = chars.by_ref().take_while(|&c| c != '=').collect();
+        let key = key.trim();
+        if key.is_empty() {
+            break;
+        }
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let value: String = match chars.next_if(|&c| c == '"' || c == '\'') {
+            Some(quote) => {
+                let value = chars.by_ref().take_while(|&c| c != quote).collect();
+                chars.by_ref().take_while(|&c| c != ',').for_each(drop);
+                value
+            },
+            None => chars.by_ref().take_while(|&c| c != ',').collect::<String>().trim().to_owned(),
+        };
+        variables.insert(key.into(), value.into());
+    }
+    variables
+}
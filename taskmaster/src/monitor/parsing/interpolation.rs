@@ -0,0 +1,124 @@
+use std::{env, error::Error, iter::Peekable, path::{Path, PathBuf}, str::Chars};
+
+/// Built-in variables available as `%(name)s` in the configuration values,
+/// along with `%(ENV_X)s` for the variable `X` of taskmaster's environment, as in supervisord.
+/// `process_num` is only known once the instances are built, until then
+/// `%(process_num)d` and `%%` are kept as they are, and the `%` of the values
+/// substituted are escaped so that the second expansion leaves them as they are.
+#[derive(Debug, Default, Clone)]
+pub struct Variables {
+    pub program_name: String,
+    pub here: String,
+    pub host_node_name: String,
+    pub process_num: Option<usize>,
+}
+
+impl Variables {
+    pub fn with_process_num(process_num: usize) -> Self {
+        Self {
+            process_num: Some(process_num),
+            ..Default::default()
+        }
+    }
+
+    /// Expands `${VAR}`, `${VAR:-default}` and `$$` from taskmaster's environment,
+    /// and the built-in `%(name)s` variables.
+    pub fn expand(&self, value: &str) -> Result<String, Box<dyn Error>> {
+        let mut expanded = String::new();
+        let mut chars = value.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match (c, chars.peek()) {
+                ('$', Some('$')) if self.process_num.is_none() => {
+                    chars.next();
+                    expanded.push('$');
+                },
+                ('$', Some('{')) if self.process_num.is_none() => {
+                    chars.next();
+                    expanded.push_str(&escape(&Self::environment(&mut chars)?));
+                },
+                ('%', Some('%')) => {
+                    chars.next();
+                    expanded.push_str(if self.process_num.is_some() { "%" } else { "%%" });
+                },
+                ('%', Some('(')) => {
+                    chars.next();
+                    expanded.push_str(&self.builtin(&mut chars)?);
+                },
+                (c, _) => expanded.push(c),
+            }
+        }
+        Ok(expanded)
+    }
+
+    pub fn expand_path(&self, path: &Path) -> Result<PathBuf, Box<dyn Error>> {
+        Ok(PathBuf::from(self.expand(&path.to_string_lossy())?))
+    }
+
+    fn environment(chars: &mut Peekable<Chars>) -> Result<String, Box<dyn Error>> {
+        let mut reference = String::new();
+        loop {
+            match chars.next() {
+                Some('}') => break,
+                Some(c) => reference.push(c),
+                None => Err("unterminated '${'")?,
+            }
+        }
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference.as_str(), None),
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            Err(format!("invalid variable name '{name}'"))?;
+        }
+        match (env::var(name), default) {
+            (Ok(value), Some(default)) if value.is_empty() => Ok(default.to_owned()),
+            (Ok(value), _) => Ok(value),
+            (Err(_), default) => Ok(default.unwrap_or_default().to_owned()),
+        }
+    }
+
+    fn builtin(&self, chars: &mut Peekable<Chars>) -> Result<String, Box<dyn Error>> {
+        let mut name = String::new();
+        loop {
+            match chars.next() {
+                Some(')') => break,
+                Some(c) => name.push(c),
+                None => Err("unterminated '%('")?,
+            }
+        }
+        let mut width = String::new();
+        while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+            width.push(c);
+        }
+        let conversion = chars.next().ok_or(format!("missing conversion after '%({name})'"))?;
+        let value = match (name.as_str(), conversion) {
+            ("program_name", 's') => self.program_name.to_owned(),
+            ("here", 's') => self.here.to_owned(),
+            ("host_node_name", 's') => self.host_node_name.to_owned(),
+            ("process_num", 'd') => match self.process_num {
+                Some(process_num) => process_num.to_string(),
+                None => return Ok(format!("%({name}){width}{conversion}")),
+            },
+            (name, 's') if name.starts_with("ENV_") => {
+                env::var(&name[4..]).map_err(|_| format!("environment variable '{}' not set for '%({name})'", &name[4..]))?
+            },
+            ("program_name" | "here" | "host_node_name" | "process_num", _) => {
+                Err(format!("invalid conversion '{conversion}' for '%({name})'"))?
+            },
+            (name, _) if name.starts_with("ENV_") => Err(format!("invalid conversion '{conversion}' for '%({name})'"))?,
+            _ => Err(format!("unknown variable '%({name})'"))?,
+        };
+        let size = width.parse::<usize>().unwrap_or(0);
+        if width.starts_with('0') {
+            Ok(escape(&format!("{value:0>size$}")))
+        } else {
+            Ok(escape(&format!("{value:>size$}")))
+        }
+    }
+}
+
+/// Value kept as is by the expansion of the instances, which turns `%%` back into `%`
+pub fn escape(value: &str) -> String {
+    value.replace('%', "%%")
+}
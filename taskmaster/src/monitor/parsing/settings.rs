@@ -0,0 +1,106 @@
+use std::{env, path::PathBuf, time::Duration};
+use serde::{Deserialize, Deserializer};
+
+use crate::monitor::logger::{Level, LogFormat, LoggerConfig};
+use crate::monitor::output::syslog::{Facility, Protocol, SyslogConfig};
+use crate::signal::Signal;
+
+use super::SettingError;
+
+const POLL_INTERVAL_DEFAULT: u64 = 300;
+// Facility of the supervisor log, in the file as with `--syslog`
+const SYSLOG_FACILITY_DEFAULT: Facility = Facility::Daemon;
+
+fn syslog_deserialize<'de, D>(deserializer: D) -> Result<Option<SyslogConfig>, D::Error> where D: Deserializer<'de> {
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Syslog {
+        #[serde(default)]
+        protocol: Protocol,
+        facility: Option<Facility>,
+        #[serde(default)]
+        tag: String,
+        #[serde(default)]
+        socket: PathBuf,
+    }
+
+    Ok(Option::<Syslog>::deserialize(deserializer)?.map(|syslog| SyslogConfig {
+        protocol: syslog.protocol,
+        facility: syslog.facility.unwrap_or(SYSLOG_FACILITY_DEFAULT),
+        tag: syslog.tag,
+        socket: syslog.socket,
+    }))
+}
+
+/// Supervisor-wide settings, the `taskmaster` section of the config file.
+/// The same settings given on the command line take precedence over the file.
+#[derive(Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct Settings {
+    pub log_file: Option<PathBuf>,
+    pub log_level: Option<Level>,
+    pub log_format: Option<LogFormat>,
+    #[serde(deserialize_with = "syslog_deserialize")]
+    pub syslog: Option<SyslogConfig>,
+    pub reopen_signal: Option<Signal>,
+    pub childlogdir: Option<PathBuf>,
+    /// File the pid of taskmaster is written to, removed on exit
+    pub pidfile: Option<PathBuf>,
+    /// Milliseconds between two checks of the processus
+    pub poll_interval: Option<u64>,
+    /// Reloads the config when the file or one of its includes changes
+    pub auto_reload: Option<bool>,
+}
+
+impl Settings {
+    /// Syslog settings, enabling syslog with the daemon facility on first use
+    pub fn syslog(&mut self) -> &mut SyslogConfig {
+        self.syslog.get_or_insert_with(|| SyslogConfig {
+            facility: SYSLOG_FACILITY_DEFAULT,
+            ..Default::default()
+        })
+    }
+
+    /// Settings of `self` replaced by the ones set in `overrides`
+    pub fn merge(self, overrides: &Settings) -> Settings {
+        let overrides = overrides.clone();
+        Settings {
+            log_file: overrides.log_file.or(self.log_file),
+            log_level: overrides.log_level.or(self.log_level),
+            log_format: overrides.log_format.or(self.log_format),
+            syslog: overrides.syslog.or(self.syslog),
+            reopen_signal: overrides.reopen_signal.or(self.reopen_signal),
+            childlogdir: overrides.childlogdir.or(self.childlogdir),
+            pidfile: overrides.pidfile.or(self.pidfile),
+            poll_interval: overrides.poll_interval.or(self.poll_interval),
+            auto_reload: overrides.auto_reload.or(self.auto_reload),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), SettingError> {
+        if self.poll_interval == Some(0) {
+            Err(SettingError::new("poll_interval", "poll_interval must be at least 1 millisecond"))?;
+        }
+        Ok(())
+    }
+
+    pub fn logger(&self) -> LoggerConfig {
+        let default = LoggerConfig::default();
+        LoggerConfig {
+            path: self.log_file.clone().unwrap_or(default.path),
+            level: self.log_level.unwrap_or(default.level),
+            format: self.log_format.unwrap_or(default.format),
+            syslog: self.syslog.clone(),
+            reopen_signal: self.reopen_signal.unwrap_or(default.reopen_signal),
+        }
+    }
+
+    /// Directory of the output files of the programs set to `AUTO`, the temporary directory by default
+    pub fn childlogdir(&self) -> PathBuf {
+        self.childlogdir.clone().unwrap_or_else(env::temp_dir)
+    }
+
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_millis(self.poll_interval.unwrap_or(POLL_INTERVAL_DEFAULT))
+    }
+}
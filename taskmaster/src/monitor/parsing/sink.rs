@@ -0,0 +1,129 @@
+use std::{error::Error, fmt, path::PathBuf};
+use serde::Deserialize;
+
+use crate::monitor::output::syslog::SyslogConfig;
+
+use super::OutputMode;
+use super::interpolation::Variables;
+
+/// `stdout` or `stderr`: a path, completed by the `<stream>_mode`, `<stream>_maxbytes`,
+/// `<stream>_backups` and `<stream>_syslog` settings, or a list of sinks.
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[serde(untagged)]
+pub enum OutputConfig {
+    Path(PathBuf),
+    Sinks(Vec<SinkConfig>),
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        OutputConfig::Path(PathBuf::new())
+    }
+}
+
+impl OutputConfig {
+    pub fn expand(&self, variables: &Variables) -> Result<Self, Box<dyn Error>> {
+        Ok(match self {
+            OutputConfig::Path(path) => OutputConfig::Path(variables.expand_path(path)?),
+            OutputConfig::Sinks(sinks) => OutputConfig::Sinks(sinks.iter().map(|sink| sink.expand(variables)).collect::<Result<_, _>>()?),
+        })
+    }
+
+    /// Paths of the files written by the output, to be changed in place
+    pub fn paths_mut(&mut self) -> Vec<&mut PathBuf> {
+        match self {
+            OutputConfig::Path(path) => vec![path],
+            OutputConfig::Sinks(sinks) => sinks.iter_mut().filter_map(|sink| match sink {
+                SinkConfig::File(file) => Some(&mut file.file),
+                _ => None,
+            }).collect(),
+        }
+    }
+}
+
+impl fmt::Display for OutputConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OutputConfig::Path(path) => write!(f, "{}", path.display()),
+            OutputConfig::Sinks(sinks) => {
+                let sinks: Vec<String> = sinks.iter().map(|sink| sink.to_string()).collect();
+                write!(f, "[{}]", sinks.join(", "))
+            },
+        }
+    }
+}
+
+/// Sinks without settings, `syslog` uses the `syslog` settings of the program
+#[derive(Deserialize, Debug, PartialEq, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum SinkName {
+    Memory,
+    Terminal,
+    Syslog,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[serde(untagged)]
+pub enum SinkConfig {
+    Name(SinkName),
+    File(FileSinkConfig),
+    Syslog(SyslogSinkConfig),
+    Program(ProgramSinkConfig),
+}
+
+impl SinkConfig {
+    fn expand(&self, variables: &Variables) -> Result<Self, Box<dyn Error>> {
+        Ok(match self {
+            SinkConfig::File(file) => SinkConfig::File(FileSinkConfig {
+                file: variables.expand_path(&file.file)?,
+                ..file.clone()
+            }),
+            SinkConfig::Syslog(sink) => SinkConfig::Syslog(SyslogSinkConfig {
+                syslog: SyslogConfig {
+                    tag: variables.expand(&sink.syslog.tag)?,
+                    ..sink.syslog.clone()
+                },
+            }),
+            sink => sink.clone(),
+        })
+    }
+}
+
+impl fmt::Display for SinkConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SinkConfig::Name(SinkName::Memory) => write!(f, "memory"),
+            SinkConfig::Name(SinkName::Terminal) => write!(f, "terminal"),
+            SinkConfig::Name(SinkName::Syslog) => write!(f, "syslog"),
+            SinkConfig::File(file) => write!(f, "file {}", file.file.display()),
+            SinkConfig::Syslog(sink) => write!(f, "syslog {:?}", sink.syslog.protocol),
+            SinkConfig::Program(sink) => write!(f, "program {}", sink.program),
+        }
+    }
+}
+
+/// File sink, rotated once it grows past `maxbytes` if it isn't 0
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct FileSinkConfig {
+    pub file: PathBuf,
+    #[serde(default)]
+    pub mode: OutputMode,
+    #[serde(default, deserialize_with = "super::bytes_deserialize")]
+    pub maxbytes: u64,
+    #[serde(default)]
+    pub backups: usize,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct SyslogSinkConfig {
+    pub syslog: SyslogConfig,
+}
+
+/// Writes the lines to the stdin of the instances of another program, read from a pipe
+#[derive(Deserialize, Debug, PartialEq, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct ProgramSinkConfig {
+    pub program: String,
+}
@@ -0,0 +1,71 @@
+use std::{collections::HashMap, error::Error, path::{Path, PathBuf}};
+use serde_yaml::{Mapping, Value};
+
+pub const EXTENDS: &str = "extends";
+// Maps merged key by key instead of being replaced
+const MERGED_KEYS: [&str; 1] = ["env"];
+
+/// Settings shared by the programs: the `defaults` of every program and the `templates`
+/// that a program or another template `extends`. The program overrides its template, which
+/// overrides the defaults, except for the `env` maps whose variables are merged.
+#[derive(Debug, Default)]
+pub struct Templates {
+    defaults: Mapping,
+    templates: HashMap<String, (Mapping, PathBuf)>,
+}
+
+impl Templates {
+    pub fn set_defaults(&mut self, defaults: Value) -> Result<(), Box<dyn Error>> {
+        self.defaults = mapping(defaults).map_err(|err| format!("defaults: {err}"))?;
+        Ok(())
+    }
+
+    /// Adds a template of `file`, the names are shared by all the files
+    pub fn add(&mut self, name: String, template: Value, file: &Path) -> Result<(), Box<dyn Error>> {
+        if let Some((_, other)) = self.templates.get(&name) {
+            Err(format!("Template {name} is defined in both '{}' and '{}'", other.display(), file.display()))?;
+        }
+        let template = mapping(template).map_err(|err| format!("Template {name}: {err}"))?;
+        self.templates.insert(name, (template, file.to_owned()));
+        Ok(())
+    }
+
+    /// Settings of a program with its templates and the defaults applied
+    pub fn resolve(&self, program: Value) -> Result<Value, Box<dyn Error>> {
+        let mut layers = vec![mapping(program)?];
+        let mut names: Vec<String> = Vec::new();
+        while let Some(parent) = layers.last().and_then(|layer| layer.get(EXTENDS)) {
+            let parent = parent.as_str().ok_or("extends: expected the name of a template")?.to_owned();
+            if names.contains(&parent) {
+                Err(format!("extends: template {parent} is part of an extends cycle"))?;
+            }
+            let (template, _) = self.templates.get(&parent).ok_or_else(|| format!("extends: template {parent} doesn't exist"))?;
+            layers.push(template.to_owned());
+            names.push(parent);
+        }
+        let mut config = self.defaults.to_owned();
+        for layer in layers.into_iter().rev() {
+            merge(&mut config, layer);
+        }
+        config.remove(EXTENDS);
+        Ok(Value::Mapping(config))
+    }
+}
+
+fn mapping(value: Value) -> Result<Mapping, Box<dyn Error>> {
+    match value {
+        Value::Mapping(mapping) => Ok(mapping),
+        Value::Null => Ok(Mapping::new()),
+        _ => Err("expected a mapping of settings".into()),
+    }
+}
+
+fn merge(config: &mut Mapping, layer: Mapping) {
+    for (key, value) in layer {
+        let merged = key.as_str().is_some_and(|key| MERGED_KEYS.contains(&key));
+        match (config.get_mut(&key), value) {
+            (Some(Value::Mapping(base)), Value::Mapping(value)) if merged => base.extend(value),
+            (_, value) => _ = config.insert(key, value),
+        }
+    }
+}
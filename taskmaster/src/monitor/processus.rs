@@ -1,5 +1,8 @@
 use std::error::Error;
-use std::process::{Child, Command};
+use std::io::{self, Write};
+use std::os::unix::io::AsRawFd;
+use std::process::Child;
+use std::thread::JoinHandle;
 use std::time::{Duration, Instant};
 use std::fmt;
 
@@ -8,7 +11,7 @@ use crate::sys::Libc;
 
 use self::id::Id;
 
-use super::program::Program;
+use super::program::{Instance, Program};
 
 pub mod id;
 
@@ -38,21 +41,29 @@ impl fmt::Display for Status {
 pub struct Processus {
     pub id: Id,
     pub name: String,
+    pub num: usize,
     pub child: Option<Child>,
     pub retries: usize,
     pub timer: Instant,
     pub status: Status,
+    /// Threads reading the output of the child
+    captures: Vec<JoinHandle<()>>,
+    /// When the exit of the child was first seen, while its output is drained
+    exited: Option<Instant>,
 }
 
 impl Processus {
-    pub fn new(name: &str, program: &Program) -> Self {
+    pub fn new(name: &str, num: usize, program: &Program) -> Self {
         Self {
             id: Default::default(),
             name: name.to_owned(),
+            num,
             child: None,
             retries: program.config.startretries,
             timer: Instant::now(),
             status: Status::Inactive,
+            captures: Vec::new(),
+            exited: None,
         }
     }
 
@@ -74,7 +85,7 @@ impl Processus {
         Ok(())
     }
 
-    pub fn start_child(&mut self, command: &mut Command, start_retries: usize, mask: u32, restart: bool) -> Result<bool, Box<dyn Error>> {
+    pub fn start_child(&mut self, instance: &mut Instance, start_retries: usize, mask: u32, restart: bool) -> Result<bool, Box<dyn Error>> {
         if restart && self.retries == 0 {
             self.status = Status::Inactive;
             self.retries = start_retries;
@@ -85,14 +96,59 @@ impl Processus {
             if restart {
                 self.retries -= 1;
             }
-            self.child = Some(Libc::umask(command, mask).map_err(|err| {
+            let command = instance.command().map_err(|err| {
                 self.reset_child(start_retries);
-                format!("Child {} spawn failed: {err}", self.name)})?);
+                format!("Child {} spawn failed: {err}", self.name)})?;
+            let mut child = Libc::umask(command, mask).map_err(|err| {
+                self.reset_child(start_retries);
+                format!("Child {} spawn failed: {err}", self.name)})?;
+            // The recent output is the one of the last run
+            if let Some(Ok(mut recent)) = instance.recent.as_ref().map(|recent| recent.lock()) {
+                recent.clear();
+            }
+            self.captures.clear();
+            self.exited = None;
+            if let Some(merged) = instance.take_merged_output() {
+                if let Some(output) = &instance.stdout {
+                    self.captures.push(output.capture(merged, child.id()));
+                }
+            }
+            if let (Some(output), Some(stdout)) = (&instance.stdout, child.stdout.take()) {
+                self.captures.push(output.capture(stdout, child.id()));
+            }
+            if let (Some(output), Some(stderr)) = (&instance.stderr, child.stderr.take()) {
+                self.captures.push(output.capture(stderr, child.id()));
+            }
+            self.child = Some(child);
             self.start_timer();
             Ok(false)
         }
     }
 
+    /// Writes a line to the stdin of the processus without waiting, fails when the pipe is full
+    pub fn send(&mut self, text: &str) -> Result<(), Box<dyn Error>> {
+        let child = self.child.as_mut().ok_or("processus is not running")?;
+        let stdin = child.stdin.as_mut().ok_or("stdin is not a pipe")?;
+        Libc::set_nonblocking(stdin.as_raw_fd())?;
+        let line = format!("{text}\n");
+        match stdin.write(line.as_bytes()) {
+            Ok(written) if written == line.len() => Ok(()),
+            Ok(written) => Err(format!("stdin is full, only {written} of {} bytes were sent", line.len()).into()),
+            Err(err) if err.kind() == io::ErrorKind::WouldBlock => Err("stdin is full, the processus isn't reading it".into()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Waits for the capture threads of an exited child to read the output left in the pipes.
+    /// A pipe kept open by a process the child started is not waited for longer than `timeout`.
+    /// Whether the capture threads read the last output of the exited child, or `timeout`
+    /// passed since its exit was first seen. Checked again on the next ticks until then.
+    pub fn output_drained(&mut self, timeout: Duration) -> bool {
+        let exited = *self.exited.get_or_insert_with(Instant::now);
+        self.captures.retain(|capture| !capture.is_finished());
+        self.captures.is_empty() || exited.elapsed() >= timeout
+    }
+
     pub fn reset_child(&mut self, start_retries: usize) {
         self.child = None;
         self.status = Status::Inactive;
@@ -7,6 +7,12 @@ pub struct Id {
     value: usize,
 }
 
+impl Id {
+    pub fn value(&self) -> usize {
+        self.value
+    }
+}
+
 impl fmt::Display for Id {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{:^5}", self.value)
@@ -1,39 +1,111 @@
-use std::{process::{Command, Stdio}, error::Error, fs::File};
-use super::parsing::Config;
+use std::{process::{Command, Stdio}, error::Error, fs::File, os::unix::process::CommandExt, collections::BTreeMap, env};
+use std::io::{self, PipeReader};
+use std::sync::{Arc, Mutex};
+use std::sync::atomic::AtomicBool;
+use crate::sys::landlock::Landlock;
+use super::output::{LineFormat, Output, OutputLimit, RecentOutput, Stream};
+use super::output::log_file::LogFile;
+use super::output::sink::{FileSink, MemorySink, ProgramSink, Sink, SyslogSink, TerminalSink};
+use super::output::syslog::Syslog;
+use super::parsing::{Config, OutputMode, StdinConfig, dotenv};
+use super::parsing::sink::{SinkConfig, SinkName};
+
+// Command of an instance with its environment and landlock ruleset
+type InstanceCommand = (Command, BTreeMap<String, String>, Option<Landlock>);
+
+pub struct Instance {
+    pub config: Config,
+    pub command: Command,
+    pub stdout: Option<Output>,
+    pub stderr: Option<Output>,
+    pub environment: BTreeMap<String, String>,
+    pub(crate) landlock: Option<Landlock>,
+    pub recent: Option<Arc<Mutex<RecentOutput>>>,
+    merged_output: Option<PipeReader>,
+}
+
+impl Instance {
+    /// Command ready to be spawned, with the redirections that have to be opened for each spawn
+    pub fn command(&mut self) -> Result<&mut Command, Box<dyn Error>> {
+        let stdin = match &self.config.stdin {
+            StdinConfig::Inherit => Stdio::inherit(),
+            StdinConfig::Null => Stdio::null(),
+            StdinConfig::Pipe => Stdio::piped(),
+            StdinConfig::File(path) => {
+                let path = self.config.workingdir.join(path);
+                Stdio::from(File::open(&path).map_err(|err| format!("stdin = '{}' {}", path.display(), err))?)
+            },
+        };
+        // With redirect_stderr both streams are the same pipe, so the lines keep their order
+        if self.config.redirect_stderr && self.stdout.is_some() {
+            let (reader, writer) = io::pipe()?;
+            self.command.stdout(writer.try_clone()?).stderr(writer);
+            self.merged_output = Some(reader);
+        }
+        Ok(self.command.stdin(stdin))
+    }
+
+    /// Read end of the pipe shared by stdout and stderr, to take once the child is spawned.
+    /// The command's write ends are closed so that the reader sees the end of the output.
+    pub fn take_merged_output(&mut self) -> Option<PipeReader> {
+        let reader = self.merged_output.take()?;
+        self.command.stdout(Stdio::null()).stderr(Stdio::null());
+        Some(reader)
+    }
+}
 
 pub struct Program {
+    pub name: String,
     pub config: Config,
-    pub command: Option<Command>,
+    pub instances: Vec<Instance>,
     active: bool,
 }
 
 impl Program {
-    pub fn new(config: Config, command: Option<Command>, active: bool) -> Self {
+    pub fn new(name: &str, config: Config, active: bool) -> Self {
         Self {
+            name: name.to_owned(),
             config,
-            command,
+            instances: Vec::new(),
             active,
         }
     }
 
     pub fn build_command(&mut self) -> Result<(), Box<dyn Error>> {
-        let mut parts = self.config.cmd.split_whitespace();
-        let program_name = parts.next().ok_or("Missing program name")?;
-        self.command = Some(Command::new(program_name));
-        
-        self.command.as_mut().unwrap().args(parts)
-        .envs(self.config.env.iter())
-        .current_dir(&self.config.workingdir);
-        
-        let output = self.fd_setup().map_err(|err| format!("Failed to parse std's: {err}"))?;
-        
-        self.command.as_mut().unwrap()
-        .stdout(output.0)
-        .stderr(output.1);
-    
+        let mut instances = Vec::new();
+        for process_num in 0..self.config.numprocs {
+            let config = self.config.instance(process_num)?;
+            let command = self.instance_command(&config, process_num)?;
+            instances.push(command);
+        }
+        self.instances = instances;
         Ok(())
     }
 
+    pub fn output(&self, num: usize, stream: Stream) -> Option<&Output> {
+        let instance = self.instances.get(num)?;
+        match stream {
+            Stream::Stdout => instance.stdout.as_ref(),
+            Stream::Stderr => instance.stderr.as_ref(),
+        }
+    }
+
+    /// Last `count` lines kept in memory for the instance `num`, all of them if `count` is `None`,
+    /// of `stream` or of both streams
+    pub fn recent_output(&self, num: usize, count: Option<usize>, stream: Option<Stream>) -> Vec<String> {
+        match self.instances.get(num).and_then(|instance| instance.recent.as_ref()) {
+            Some(recent) => recent.lock().map(|recent| recent.tail(count, stream)).unwrap_or_default(),
+            None => Vec::new(),
+        }
+    }
+
+    /// Lines dropped and truncated by the output limits of the instance `num`
+    pub fn suppressed(&self, num: usize) -> (u64, u64) {
+        [Stream::Stdout, Stream::Stderr].into_iter()
+            .filter_map(|stream| self.output(num, stream)?.limit.as_ref()?.lock().ok().map(|limit| (limit.dropped, limit.truncated)))
+            .fold((0, 0), |(dropped, truncated), (d, t)| (dropped + d, truncated + t))
+    }
+
     pub fn is_active(&self) -> bool {
         self.active
     }
@@ -50,20 +122,142 @@ impl Program {
         format!("{prefix}{name}")
     }
 
-    fn fd_setup(&self) -> Result<(Stdio, Stdio), Box<dyn Error>> {
-        let stdout = if self.config.stdout.as_os_str().is_empty() {
-            Stdio::null()
+    /// Applies a config that only differs by its scheduling settings to the next spawns,
+    /// keeping the outputs of the running instances open
+    pub fn set_scheduling(&mut self, config: Config) -> Result<(), Box<dyn Error>> {
+        let mut commands = Vec::new();
+        for (process_num, instance) in self.instances.iter().enumerate() {
+            let instance_config = config.instance(process_num)?;
+            commands.push((Self::command(&instance_config, instance.stdout.is_some(), instance.stderr.is_some())?, instance_config));
+        }
+        for (instance, ((command, environment, landlock), instance_config)) in self.instances.iter_mut().zip(commands) {
+            instance.command = command;
+            instance.environment = environment;
+            instance.landlock = landlock;
+            instance.config = instance_config;
+        }
+        self.config = config;
+        Ok(())
+    }
+
+    fn instance_command(&self, config: &Config, process_num: usize) -> Result<Instance, Box<dyn Error>> {
+        let memory = [Stream::Stdout, Stream::Stderr].into_iter().any(|stream| config.sinks(stream).contains(&SinkConfig::Name(SinkName::Memory)));
+        let recent = (memory && config.output_buffer > 0).then(|| Arc::new(Mutex::new(RecentOutput::new(config.output_buffer as usize))));
+        let (stdout, stderr) = self.fd_setup(config, process_num, &recent).map_err(|err| format!("Failed to parse std's: {err}"))?;
+        let (command, environment, landlock) = Self::command(config, stdout.is_some(), stderr.is_some())?;
+
+        Ok(Instance {
+            config: config.clone(),
+            command,
+            stdout,
+            stderr,
+            environment,
+            landlock,
+            recent,
+            merged_output: None,
+        })
+    }
+
+    /// The captured streams are piped
+    fn command(config: &Config, stdout: bool, stderr: bool) -> Result<InstanceCommand, Box<dyn Error>> {
+        let argv = config.cmd.argv(config.shell)?;
+        let mut command = Command::new(&argv[0]);
+        let environment = Self::environment(config)?;
+
+        command.args(&argv[1..])
+        .env_clear()
+        .envs(environment.iter())
+        .current_dir(&config.workingdir)
+        .stdout(if stdout { Stdio::piped() } else { Stdio::null() })
+        .stderr(if stderr { Stdio::piped() } else { Stdio::null() });
+
+        let scheduling = config.scheduling()?;
+        if !scheduling.is_default() {
+            unsafe {
+                command.pre_exec(move || scheduling.apply_self());
+            }
+        }
+        // After the scheduling settings, as writing oom_score_adj may not be allowed by the ruleset
+        let landlock = Self::landlock_setup(config, &mut command)?;
+        Ok((command, environment, landlock))
+    }
+
+    /// Environment of the child: taskmaster's own environment, or only the passthrough
+    /// variables with `clear_env`, then the env files in order, then `env`.
+    fn environment(config: &Config) -> Result<BTreeMap<String, String>, Box<dyn Error>> {
+        let mut environment: BTreeMap<String, String> = if config.clear_env {
+            config.env_passthrough.iter()
+            .filter_map(|name| env::var(name).ok().map(|value| (name.to_owned(), value)))
+            .collect()
         } else {
-            let s = self.config.workingdir.clone().join(&self.config.stdout);
-            Stdio::from(File::create(&s).map_err(|err| format!("stdout = '{}' {}", s.display(), err))?)
+            env::vars_os().filter_map(|(key, value)| Some((key.into_string().ok()?, value.into_string().ok()?))).collect()
         };
-        let stderr = if self.config.stderr.as_os_str().is_empty() {
-            Stdio::null()
+        for path in &config.env_file {
+            environment.extend(dotenv::read(&config.workingdir.join(path))?);
+        }
+        environment.extend(config.env.iter().map(|(key, value)| (key.to_owned(), value.to_owned())));
+        Ok(environment)
+    }
+
+    fn landlock_setup(config: &Config, command: &mut Command) -> Result<Option<Landlock>, Box<dyn Error>> {
+        if let Some(landlock) = &config.landlock {
+            let workingdir = &config.workingdir;
+            let read_only: Vec<_> = landlock.read_only.iter().map(|path| workingdir.join(path)).collect();
+            let read_write: Vec<_> = landlock.read_write.iter().map(|path| workingdir.join(path)).collect();
+            let ruleset = Landlock::new(&read_only, &read_write)?;
+            let restrict = ruleset.clone();
+            unsafe {
+                command.pre_exec(move || restrict.restrict());
+            }
+            Ok(Some(ruleset))
         } else {
-            let s = self.config.workingdir.clone().join(&self.config.stderr);
-            Stdio::from(File::create(&s).map_err(|err| format!("stderr = '{}' {}", s.display(), err))?)
+            Ok(None)
+        }
+    }
+
+    /// Outputs of the streams, captured if they have at least one sink
+    fn fd_setup(&self, config: &Config, process_num: usize, recent: &Option<Arc<Mutex<RecentOutput>>>) -> Result<(Option<Output>, Option<Output>), Box<dyn Error>> {
+        let format = LineFormat::new(&config.output_format, &config.output_time_format)?;
+        let output = |stream: Stream| -> Result<Option<Output>, Box<dyn Error>> {
+            let sinks = config.sinks(stream).iter()
+                .map(|sink| self.sink(config, sink, recent).map_err(|err| format!("{stream} {sink}: {err}")))
+                .collect::<Result<Vec<_>, _>>()?;
+            if sinks.is_empty() {
+                return Ok(None);
+            }
+            let limit = (config.max_line_length > 0 || config.log_rate_limit.is_some())
+                .then(|| Arc::new(Mutex::new(OutputLimit::new(config.max_line_length as usize, config.log_rate_limit.clone()))));
+            Ok(Some(Output {
+                program: self.name.to_owned(),
+                instance: process_num,
+                stream,
+                format: format.clone(),
+                sinks,
+                limit,
+                follow: Arc::new(AtomicBool::new(false)),
+            }))
         };
 
-        Ok((stdout, stderr))
+        let stdout = output(Stream::Stdout)?;
+        // The stderr settings are unused when it is written with stdout
+        if config.redirect_stderr {
+            return Ok((stdout, None));
+        }
+        Ok((stdout, output(Stream::Stderr)?))
     }
-}
\ No newline at end of file
+
+    fn sink(&self, config: &Config, sink: &SinkConfig, recent: &Option<Arc<Mutex<RecentOutput>>>) -> Result<Arc<dyn Sink>, Box<dyn Error>> {
+        Ok(match sink {
+            SinkConfig::File(file) => {
+                let path = config.workingdir.join(&file.file);
+                let file = LogFile::open(&path, file.mode == OutputMode::Truncate, file.maxbytes, file.backups).map_err(|err| format!("'{}' {err}", path.display()))?;
+                Arc::new(FileSink::new(file))
+            },
+            SinkConfig::Name(SinkName::Syslog) => Arc::new(SyslogSink::new(Syslog::connect(&config.syslog, &self.name)?)),
+            SinkConfig::Syslog(sink) => Arc::new(SyslogSink::new(Syslog::connect(&sink.syslog, &self.name)?)),
+            SinkConfig::Name(SinkName::Memory) => Arc::new(MemorySink::new(recent.clone().ok_or("output_buffer is 0")?)),
+            SinkConfig::Name(SinkName::Terminal) => Arc::new(TerminalSink),
+            SinkConfig::Program(sink) => Arc::new(ProgramSink::new(&sink.program)),
+        })
+    }
+}
@@ -40,6 +40,31 @@ pub enum Signal {
 }
 
 impl Signal {
+    /// Number of the signal on this platform, the variants use the BSD numbering
+    #[cfg(target_os = "linux")]
+    pub fn number(self) -> i32 {
+        match self {
+            Signal::SIGBUS => 7,
+            Signal::SIGUSR1 => 10,
+            Signal::SIGUSR2 => 12,
+            Signal::SIGCHLD => 17,
+            Signal::SIGCONT => 18,
+            Signal::SIGSTOP => 19,
+            Signal::SIGTSTP => 20,
+            Signal::SIGURG => 23,
+            Signal::SIGIO => 29,
+            Signal::SIGSYS => 31,
+            // Not available on Linux, rejected by the kernel
+            Signal::SIGEMT | Signal::SIGINFO => -1,
+            signal => signal as i32,
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    pub fn number(self) -> i32 {
+        self as i32
+    }
+
     pub fn parse(value: &str) -> Result<Signal, Box<dyn Error>> {
         match value {
             "SIGHUP" => Ok(Signal::SIGHUP),
@@ -1,18 +1,52 @@
-use std::{error::Error, process::{Child, Command}, sync::atomic::AtomicBool};
+use std::{error::Error, ffi::CString, io, os::unix::ffi::OsStrExt, path::Path, process::{Child, Command}, sync::atomic::AtomicBool};
 use crate::signal::Signal;
 
+pub mod inotify;
+pub mod landlock;
+pub mod scheduling;
+
 extern "C" {
     fn umask(mask: u32) -> u32;
     fn kill(pid: i32, sig: i32) -> i32;
+    fn gethostname(name: *mut u8, len: usize) -> i32;
+    fn time(t: *mut i64) -> i64;
+    fn localtime_r(t: *const i64, tm: *mut Tm) -> *mut Tm;
+    fn strftime(s: *mut u8, max: usize, format: *const i8, tm: *const Tm) -> usize;
+    fn strptime(s: *const i8, format: *const i8, tm: *mut Tm) -> *const i8;
+    fn mktime(tm: *mut Tm) -> i64;
+    fn access(path: *const i8, mode: i32) -> i32;
+    fn fcntl(fd: i32, cmd: i32, ...) -> i32;
     fn signal(
         signum: i32, 
         handler: usize,
     ) -> usize;
 }
 
+#[repr(C)]
+struct Tm {
+    tm_sec: i32,
+    tm_min: i32,
+    tm_hour: i32,
+    tm_mday: i32,
+    tm_mon: i32,
+    tm_year: i32,
+    tm_wday: i32,
+    tm_yday: i32,
+    tm_isdst: i32,
+    tm_gmtoff: i64,
+    tm_zone: *const i8,
+}
+
+pub const W_OK: i32 = 2;
+pub const X_OK: i32 = 1;
+const F_GETFL: i32 = 3;
+const F_SETFL: i32 = 4;
+const O_NONBLOCK: i32 = 0o4000;
+
 const SIG_ERR: usize = 18_446_744_073_709_551_615usize;
 
 pub static RELOAD_INSTRUCTION: AtomicBool = AtomicBool::new(false);
+pub static REOPEN_INSTRUCTION: AtomicBool = AtomicBool::new(false);
 
 pub struct Libc;
 
@@ -34,16 +68,84 @@ impl Libc {
 
     pub fn kill(child: &mut Option<Child>, sig: Signal) -> Result<(), Box<dyn Error>> {
         unsafe {
-            if kill(child.as_mut().ok_or("child process does not exist")?.id() as i32, sig as i32) != 0 {
+            if kill(child.as_mut().ok_or("child process does not exist")?.id() as i32, sig.number()) != 0 {
                 return Err("failed to kill process".into());
             }
         }
         Ok(())
     }
 
+    pub fn gethostname() -> Result<String, Box<dyn Error>> {
+        let mut buffer = [0u8; 256];
+        unsafe {
+            if gethostname(buffer.as_mut_ptr(), buffer.len()) != 0 {
+                return Err("failed to get the host name".into());
+            }
+        }
+        let len = buffer.iter().position(|&c| c == 0).unwrap_or(buffer.len());
+        Ok(String::from_utf8_lossy(&buffer[..len]).into_owned())
+    }
+
+    /// Current local time formatted with the strftime `format`
+    pub fn strftime(format: &str) -> String {
+        let format = match CString::new(format) {
+            Ok(format) => format,
+            Err(_) => return String::new(),
+        };
+        let mut buffer = [0u8; 256];
+        let len = unsafe {
+            let now = time(std::ptr::null_mut());
+            let mut tm: Tm = std::mem::zeroed();
+            if localtime_r(&now, &mut tm).is_null() {
+                return String::new();
+            }
+            strftime(buffer.as_mut_ptr(), buffer.len(), format.as_ptr(), &tm)
+        };
+        String::from_utf8_lossy(&buffer[..len]).into_owned()
+    }
+
+    /// Parses the start of `text` with the strptime `format`, fields missing from the format
+    /// are taken from the current date. Returns the timestamp and the length parsed.
+    pub fn strptime(text: &str, format: &str) -> Option<(i64, usize)> {
+        let (text, format) = (CString::new(text).ok()?, CString::new(format).ok()?);
+        unsafe {
+            let now = time(std::ptr::null_mut());
+            let mut tm: Tm = std::mem::zeroed();
+            if localtime_r(&now, &mut tm).is_null() {
+                return None;
+            }
+            let end = strptime(text.as_ptr(), format.as_ptr(), &mut tm);
+            if end.is_null() {
+                return None;
+            }
+            tm.tm_isdst = -1;
+            let timestamp = mktime(&mut tm);
+            (timestamp != -1).then(|| (timestamp, end.offset_from(text.as_ptr()) as usize))
+        }
+    }
+
+    /// Whether taskmaster has the permissions of `mode` on `path`
+    pub fn access(path: &Path, mode: i32) -> bool {
+        match CString::new(path.as_os_str().as_bytes()) {
+            Ok(path) => unsafe { access(path.as_ptr(), mode) == 0 },
+            Err(_) => false,
+        }
+    }
+
+    /// Makes the reads and writes of `fd` fail with `WouldBlock` instead of waiting
+    pub fn set_nonblocking(fd: i32) -> io::Result<()> {
+        unsafe {
+            let flags = fcntl(fd, F_GETFL);
+            if flags < 0 || fcntl(fd, F_SETFL, flags | O_NONBLOCK) < 0 {
+                return Err(io::Error::last_os_error());
+            }
+        }
+        Ok(())
+    }
+
     pub fn signal(sig: Signal, fn_sig_handler: fn(i32)) -> Result<(), Box<dyn Error>> {
         unsafe {
-            if signal(sig as i32, fn_sig_handler as usize) == SIG_ERR {
+            if signal(sig.number(), fn_sig_handler as usize) == SIG_ERR {
                 return Err("the call to signal funtion failed".into());
             }
         }
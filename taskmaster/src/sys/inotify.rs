@@ -0,0 +1,101 @@
+use std::{error::Error, path::{Path, PathBuf}};
+#[cfg(target_os = "linux")]
+use std::{ffi::{CString, OsStr}, io, os::unix::ffi::OsStrExt};
+
+// inotify is specific to Linux, elsewhere the config files aren't watched
+#[cfg(target_os = "linux")]
+mod linux {
+    extern "C" {
+        pub fn inotify_init1(flags: i32) -> i32;
+        pub fn inotify_add_watch(fd: i32, path: *const i8, mask: u32) -> i32;
+        pub fn read(fd: i32, buf: *mut u8, count: usize) -> isize;
+        pub fn close(fd: i32) -> i32;
+    }
+
+    pub const IN_NONBLOCK: i32 = 0o4000;
+    pub const IN_CLOEXEC: i32 = 0o2000000;
+    pub const IN_CLOSE_WRITE: u32 = 0x8;
+    pub const IN_MOVED_TO: u32 = 0x80;
+    pub const IN_CREATE: u32 = 0x100;
+    pub const IN_DELETE: u32 = 0x200;
+    pub const IN_Q_OVERFLOW: u32 = 0x4000;
+    // wd, mask, cookie and len, followed by the name padded with nul bytes
+    pub const EVENT_HEADER: usize = 16;
+}
+#[cfg(target_os = "linux")]
+use linux::*;
+
+/// Whether the files can be watched on this platform
+pub const SUPPORTED: bool = cfg!(target_os = "linux");
+
+/// Watches the files written, created, renamed or deleted in directories, so that a file replaced
+/// by an editor, written to a temporary file and renamed, keeps being watched.
+#[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+pub struct Inotify {
+    fd: i32,
+    dirs: Vec<(i32, PathBuf)>,
+}
+
+#[cfg(target_os = "linux")]
+impl Inotify {
+    pub fn new(dirs: &[PathBuf]) -> Result<Self, Box<dyn Error>> {
+        let fd = unsafe { inotify_init1(IN_NONBLOCK | IN_CLOEXEC) };
+        if fd < 0 {
+            Err(format!("inotify: {}", io::Error::last_os_error()))?;
+        }
+        let mut inotify = Inotify { fd, dirs: Vec::new() };
+        for dir in dirs {
+            let path = CString::new(dir.as_os_str().as_bytes())?;
+            let wd = unsafe { inotify_add_watch(fd, path.as_ptr(), IN_CLOSE_WRITE | IN_MOVED_TO | IN_CREATE | IN_DELETE) };
+            if wd < 0 {
+                Err(format!("inotify: '{}' {}", dir.display(), io::Error::last_os_error()))?;
+            }
+            inotify.dirs.push((wd, dir.to_owned()));
+        }
+        Ok(inotify)
+    }
+
+    /// Whether a file for which `watched` is true changed since the last call, reads every pending event
+    pub fn changed(&mut self, watched: impl Fn(&Path) -> bool) -> bool {
+        let mut changed = false;
+        let mut buffer = [0u8; 4096];
+        loop {
+            let len = unsafe { read(self.fd, buffer.as_mut_ptr(), buffer.len()) };
+            if len <= 0 {
+                return changed;
+            }
+            let mut events = &buffer[..len as usize];
+            while events.len() >= EVENT_HEADER {
+                let field = |index: usize| u32::from_ne_bytes([events[index], events[index + 1], events[index + 2], events[index + 3]]);
+                let (wd, mask, name_len) = (field(0) as i32, field(4), field(12) as usize);
+                let name = &events[EVENT_HEADER..EVENT_HEADER + name_len];
+                let name = &name[..name.iter().position(|&c| c == 0).unwrap_or(name.len())];
+                // A directory given twice has a single watch
+                changed |= mask & IN_Q_OVERFLOW != 0 || self.dirs.iter().any(|(dir_wd, dir)| {
+                    *dir_wd == wd && watched(&dir.join(OsStr::from_bytes(name)))
+                });
+                events = &events[EVENT_HEADER + name_len..];
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+impl Inotify {
+    pub fn new(_dirs: &[PathBuf]) -> Result<Self, Box<dyn Error>> {
+        Err("inotify is not supported on this platform")?
+    }
+
+    pub fn changed(&mut self, _watched: impl Fn(&Path) -> bool) -> bool {
+        false
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Drop for Inotify {
+    fn drop(&mut self) {
+        unsafe {
+            close(self.fd);
+        }
+    }
+}
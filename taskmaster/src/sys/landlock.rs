@@ -0,0 +1,200 @@
+use std::{error::Error, ffi::CString, fmt, io, os::unix::ffi::OsStrExt, path::{Path, PathBuf}};
+
+// Landlock is a Linux security module, the syscalls have the Linux numbers
+#[cfg(target_os = "linux")]
+mod linux {
+    extern "C" {
+        pub fn syscall(number: i64, ...) -> i64;
+        pub fn prctl(option: i32, ...) -> i32;
+        pub fn open(path: *const i8, flags: i32, ...) -> i32;
+        pub fn close(fd: i32) -> i32;
+    }
+
+    pub const SYS_LANDLOCK_CREATE_RULESET: i64 = 444;
+    pub const SYS_LANDLOCK_ADD_RULE: i64 = 445;
+    pub const SYS_LANDLOCK_RESTRICT_SELF: i64 = 446;
+    pub const LANDLOCK_CREATE_RULESET_VERSION: u32 = 1;
+    pub const LANDLOCK_RULE_PATH_BENEATH: i32 = 1;
+    pub const PR_SET_NO_NEW_PRIVS: i32 = 38;
+    pub const O_PATH: i32 = 0o10000000;
+    pub const O_CLOEXEC: i32 = 0o2000000;
+
+    #[repr(C)]
+    pub struct RulesetAttr {
+        pub handled_access_fs: u64,
+    }
+
+    #[repr(C, packed)]
+    pub struct PathBeneathAttr {
+        pub allowed_access: u64,
+        pub parent_fd: i32,
+    }
+}
+#[cfg(target_os = "linux")]
+use linux::*;
+
+const ACCESS_EXECUTE: u64 = 1 << 0;
+const ACCESS_WRITE_FILE: u64 = 1 << 1;
+const ACCESS_READ_FILE: u64 = 1 << 2;
+const ACCESS_READ_DIR: u64 = 1 << 3;
+const ACCESS_REFER: u64 = 1 << 13;
+const ACCESS_TRUNCATE: u64 = 1 << 14;
+// Every right of the first ABI, from EXECUTE up to MAKE_SYM
+const ACCESS_ABI_V1: u64 = (1 << 13) - 1;
+const ACCESS_FILE: u64 = ACCESS_EXECUTE | ACCESS_WRITE_FILE | ACCESS_READ_FILE | ACCESS_TRUNCATE;
+const ACCESS_READ_ONLY: u64 = ACCESS_EXECUTE | ACCESS_READ_FILE | ACCESS_READ_DIR;
+
+const ACCESS_NAMES: [&str; 15] = [
+    "execute", "write_file", "read_file", "read_dir", "remove_dir", "remove_file", "make_char",
+    "make_dir", "make_reg", "make_sock", "make_fifo", "make_block", "make_sym", "refer", "truncate",
+];
+
+#[derive(Debug, Clone)]
+struct Rule {
+    path: PathBuf,
+    #[cfg_attr(not(target_os = "linux"), allow(dead_code))]
+    c_path: CString,
+    access: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Landlock {
+    abi: i64,
+    handled: u64,
+    rules: Vec<Rule>,
+}
+
+impl Landlock {
+    pub fn new(read_only: &[PathBuf], read_write: &[PathBuf]) -> Result<Self, Box<dyn Error>> {
+        let abi = Self::abi();
+        let handled = match abi {
+            ..=0 => 0,
+            1 => ACCESS_ABI_V1,
+            2 => ACCESS_ABI_V1 | ACCESS_REFER,
+            _ => ACCESS_ABI_V1 | ACCESS_REFER | ACCESS_TRUNCATE,
+        };
+        let mut rules = Vec::new();
+        for (paths, access) in [(read_only, ACCESS_READ_ONLY), (read_write, handled)] {
+            for path in paths {
+                rules.push(Self::rule(path, access & handled)?);
+            }
+        }
+        Ok(Self {
+            abi,
+            handled,
+            rules,
+        })
+    }
+
+    pub fn is_supported(&self) -> bool {
+        self.abi > 0
+    }
+
+    // Called in the child between fork and exec, so it only uses the prepared paths and raw syscalls
+    pub fn restrict(&self) -> io::Result<()> {
+        if !self.is_supported() {
+            return Ok(());
+        }
+        self.enforce()
+    }
+
+    #[cfg(target_os = "linux")]
+    fn enforce(&self) -> io::Result<()> {
+        let attr = RulesetAttr { handled_access_fs: self.handled };
+        let ruleset = unsafe {
+            syscall(SYS_LANDLOCK_CREATE_RULESET, &attr as *const RulesetAttr, std::mem::size_of::<RulesetAttr>(), 0u32)
+        };
+        if ruleset < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let ruleset = ruleset as i32;
+        let result = self.add_rules(ruleset);
+        let result = result.and_then(|_| unsafe {
+            if prctl(PR_SET_NO_NEW_PRIVS, 1u64, 0u64, 0u64, 0u64) != 0
+                || syscall(SYS_LANDLOCK_RESTRICT_SELF, ruleset, 0u32) != 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        });
+        unsafe {
+            close(ruleset);
+        }
+        result
+    }
+
+    // Never called, the ABI is 0 where landlock doesn't exist
+    #[cfg(not(target_os = "linux"))]
+    fn enforce(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn add_rules(&self, ruleset: i32) -> io::Result<()> {
+        for rule in &self.rules {
+            let fd = unsafe { open(rule.c_path.as_ptr(), O_PATH | O_CLOEXEC) };
+            if fd < 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let attr = PathBeneathAttr { allowed_access: rule.access, parent_fd: fd };
+            let res = unsafe {
+                syscall(SYS_LANDLOCK_ADD_RULE, ruleset, LANDLOCK_RULE_PATH_BENEATH, &attr as *const PathBeneathAttr, 0u32)
+            };
+            let err = io::Error::last_os_error();
+            unsafe {
+                close(fd);
+            }
+            if res != 0 {
+                return Err(err);
+            }
+        }
+        Ok(())
+    }
+
+    #[cfg(target_os = "linux")]
+    fn abi() -> i64 {
+        unsafe {
+            syscall(SYS_LANDLOCK_CREATE_RULESET, std::ptr::null::<RulesetAttr>(), 0usize, LANDLOCK_CREATE_RULESET_VERSION)
+        }
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    fn abi() -> i64 {
+        0
+    }
+
+    fn rule(path: &Path, access: u64) -> Result<Rule, Box<dyn Error>> {
+        let path = path.canonicalize().map_err(|err| format!("landlock: '{}' {}", path.display(), err))?;
+        let metadata = path.metadata()?;
+        let access = if metadata.is_dir() { access } else { access & ACCESS_FILE };
+        Ok(Rule {
+            c_path: CString::new(path.as_os_str().as_bytes())?,
+            path,
+            access,
+        })
+    }
+
+    fn access_names(access: u64) -> String {
+        ACCESS_NAMES.iter().enumerate()
+            .filter(|(bit, _)| access & (1 << bit) != 0)
+            .map(|(_, name)| *name)
+            .collect::<Vec<&str>>()
+            .join(",")
+    }
+}
+
+impl fmt::Display for Landlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if !cfg!(target_os = "linux") {
+            return write!(f, "not supported on this platform, not enforced");
+        }
+        if !self.is_supported() {
+            return write!(f, "not supported by the kernel, not enforced");
+        }
+        write!(f, "enforced with ABI v{}, handled: {}", self.abi, Self::access_names(self.handled))?;
+        for rule in &self.rules {
+            write!(f, "\n            {}: {}", rule.path.display(), Self::access_names(rule.access))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,262 @@
+use std::{error::Error, fmt, io};
+#[cfg(target_os = "linux")]
+use std::fs;
+use serde::Deserialize;
+
+// The scheduling syscalls and `/proc` are Linux ones
+#[cfg(target_os = "linux")]
+mod linux {
+    extern "C" {
+        pub fn syscall(number: i64, ...) -> i64;
+        pub fn setpriority(which: i32, who: u32, prio: i32) -> i32;
+        pub fn sched_setaffinity(pid: i32, cpusetsize: usize, mask: *const u64) -> i32;
+        pub fn sched_getaffinity(pid: i32, cpusetsize: usize, mask: *mut u64) -> i32;
+        pub fn open(path: *const i8, flags: i32, ...) -> i32;
+        pub fn write(fd: i32, buf: *const u8, count: usize) -> isize;
+        pub fn close(fd: i32) -> i32;
+    }
+
+    pub const IOPRIO_WHO_PROCESS: i32 = 1;
+    pub const IOPRIO_CLASS_SHIFT: u32 = 13;
+    pub const PRIO_PROCESS: i32 = 0;
+    pub const O_WRONLY: i32 = 1;
+    pub const O_CLOEXEC: i32 = 0o2000000;
+    pub const OOM_SCORE_ADJ_SELF: &[u8] = b"/proc/self/oom_score_adj\0";
+}
+#[cfg(target_os = "linux")]
+use linux::*;
+
+// The numbers of ioprio_set and ioprio_get differ on each architecture, ionice is refused where they aren't known
+#[cfg(all(target_os = "linux", target_arch = "x86_64"))]
+const SYS_IOPRIO: Option<(i64, i64)> = Some((251, 252));
+#[cfg(all(target_os = "linux", target_arch = "aarch64"))]
+const SYS_IOPRIO: Option<(i64, i64)> = Some((30, 31));
+#[cfg(not(all(target_os = "linux", any(target_arch = "x86_64", target_arch = "aarch64"))))]
+const SYS_IOPRIO: Option<(i64, i64)> = None;
+const CPU_SET_WORDS: usize = 16;
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "kebab-case")]
+#[repr(u32)]
+pub enum IoniceClass {
+    None = 0,
+    Realtime = 1,
+    BestEffort = 2,
+    Idle = 3,
+}
+
+/// Scheduling settings of a process, `None` and an empty affinity leave the inherited values
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Scheduling {
+    pub nice: Option<i32>,
+    pub ionice: Option<(IoniceClass, u32)>,
+    pub cpu_affinity: Vec<usize>,
+    pub oom_score_adj: Option<i32>,
+}
+
+impl Scheduling {
+    pub fn new(nice: Option<i32>, ionice: Option<(IoniceClass, u32)>, cpu_affinity: Vec<usize>, oom_score_adj: Option<i32>) -> Result<Self, Box<dyn Error>> {
+        let set = nice.is_some() || ionice.is_some() || !cpu_affinity.is_empty() || oom_score_adj.is_some();
+        if set && !cfg!(target_os = "linux") {
+            Err("scheduling settings are not supported on this platform")?;
+        }
+        if let Some(nice) = nice.filter(|nice| !(-20..=19).contains(nice)) {
+            Err(format!("nice {nice} out of range -20..19"))?;
+        }
+        if ionice.is_some() && SYS_IOPRIO.is_none() {
+            Err("ionice is not supported on this architecture")?;
+        }
+        if let Some((_, level)) = ionice.filter(|(_, level)| *level > 7) {
+            Err(format!("ionice_level {level} out of range 0..7"))?;
+        }
+        if let Some(cpu) = cpu_affinity.iter().find(|&&cpu| cpu >= CPU_SET_WORDS * 64) {
+            Err(format!("cpu_affinity {cpu} out of range 0..{}", CPU_SET_WORDS * 64 - 1))?;
+        }
+        if let Some(oom_score_adj) = oom_score_adj.filter(|adj| !(-1000..=1000).contains(adj)) {
+            Err(format!("oom_score_adj {oom_score_adj} out of range -1000..1000"))?;
+        }
+        Ok(Self {
+            nice,
+            ionice,
+            cpu_affinity,
+            oom_score_adj,
+        })
+    }
+
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl Scheduling {
+    // Called in the child between fork and exec, so it only uses raw syscalls and no allocation
+    pub fn apply_self(&self) -> io::Result<()> {
+        Self::check(self.nice.map_or(0, |nice| unsafe { setpriority(PRIO_PROCESS, 0, nice) }))?;
+        Self::check(self.ionice.map_or(0, |ionice| Self::ioprio_set(0, Self::ioprio(ionice))))?;
+        if !self.cpu_affinity.is_empty() {
+            let mask = self.cpu_set();
+            Self::check(unsafe { sched_setaffinity(0, std::mem::size_of_val(&mask), mask.as_ptr()) })?;
+        }
+        if let Some(oom_score_adj) = self.oom_score_adj {
+            let mut buffer = [0u8; 12];
+            let value = Self::format_i32(oom_score_adj, &mut buffer);
+            unsafe {
+                let fd = open(OOM_SCORE_ADJ_SELF.as_ptr() as *const i8, O_WRONLY | O_CLOEXEC);
+                Self::check(fd)?;
+                let written = write(fd, value.as_ptr(), value.len());
+                close(fd);
+                Self::check(written as i32)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Applies the settings that differ from `old` to every thread of a running process. A setting
+    /// that was removed is put back to taskmaster's own value, which the processus inherited.
+    pub fn apply(&self, pid: u32, old: &Scheduling) -> Result<(), Box<dyn Error>> {
+        let tids: Vec<u32> = fs::read_dir(format!("/proc/{pid}/task"))?
+            .filter_map(|entry| entry.ok()?.file_name().to_str()?.parse().ok())
+            .collect();
+        let nice = (self.nice != old.nice)
+            .then(|| self.nice.map_or_else(Self::own_nice, Ok))
+            .transpose().map_err(|err| format!("nice: {err}"))?;
+        let ioprio = (self.ionice != old.ionice && SYS_IOPRIO.is_some())
+            .then(|| self.ionice.map_or_else(Self::own_ioprio, |ionice| Ok(Self::ioprio(ionice))))
+            .transpose().map_err(|err| format!("ionice: {err}"))?;
+        let mask = (self.cpu_affinity != old.cpu_affinity)
+            .then(|| if self.cpu_affinity.is_empty() { Self::own_cpu_set() } else { Ok(self.cpu_set()) })
+            .transpose().map_err(|err| format!("cpu_affinity: {err}"))?;
+        for tid in tids {
+            if let Some(nice) = nice {
+                Self::check(unsafe { setpriority(PRIO_PROCESS, tid, nice) })
+                    .map_err(|err| format!("nice: {err}"))?;
+            }
+            if let Some(ioprio) = ioprio {
+                Self::check(Self::ioprio_set(tid, ioprio))
+                    .map_err(|err| format!("ionice: {err}"))?;
+            }
+            if let Some(mask) = mask {
+                Self::check(unsafe { sched_setaffinity(tid as i32, std::mem::size_of_val(&mask), mask.as_ptr()) })
+                    .map_err(|err| format!("cpu_affinity: {err}"))?;
+            }
+        }
+        if self.oom_score_adj != old.oom_score_adj {
+            let oom_score_adj = match self.oom_score_adj {
+                Some(oom_score_adj) => oom_score_adj.to_string(),
+                None => fs::read_to_string("/proc/self/oom_score_adj").map_err(|err| format!("oom_score_adj: {err}"))?,
+            };
+            fs::write(format!("/proc/{pid}/oom_score_adj"), oom_score_adj.trim())
+                .map_err(|err| format!("oom_score_adj: {err}"))?;
+        }
+        Ok(())
+    }
+
+    // Only called when the syscall is known, `Scheduling::new` refuses ionice otherwise
+    fn ioprio_set(tid: u32, ioprio: u32) -> i32 {
+        match SYS_IOPRIO {
+            Some((set, _)) => unsafe { syscall(set, IOPRIO_WHO_PROCESS, tid, ioprio) as i32 },
+            None => -1,
+        }
+    }
+
+    fn own_ioprio() -> io::Result<u32> {
+        let ioprio = match SYS_IOPRIO {
+            Some((_, get)) => unsafe { syscall(get, IOPRIO_WHO_PROCESS, 0) as i32 },
+            None => -1,
+        };
+        Self::check(ioprio)?;
+        Ok(ioprio as u32)
+    }
+
+    fn own_nice() -> io::Result<i32> {
+        let stat = fs::read_to_string("/proc/self/stat")?;
+        // The nice value is the 19th field, the 17th after the command name in parentheses
+        stat.rsplit_once(')')
+            .and_then(|(_, fields)| fields.split_whitespace().nth(16)?.parse().ok())
+            .ok_or_else(|| io::Error::other("invalid /proc/self/stat"))
+    }
+
+    fn ioprio((class, level): (IoniceClass, u32)) -> u32 {
+        match class {
+            IoniceClass::None => 0,
+            IoniceClass::Idle => (class as u32) << IOPRIO_CLASS_SHIFT,
+            _ => (class as u32) << IOPRIO_CLASS_SHIFT | level,
+        }
+    }
+
+    fn cpu_set(&self) -> [u64; CPU_SET_WORDS] {
+        let mut mask = [0u64; CPU_SET_WORDS];
+        for cpu in &self.cpu_affinity {
+            mask[cpu / 64] |= 1 << (cpu % 64);
+        }
+        mask
+    }
+
+    fn own_cpu_set() -> io::Result<[u64; CPU_SET_WORDS]> {
+        let mut mask = [0u64; CPU_SET_WORDS];
+        Self::check(unsafe { sched_getaffinity(0, std::mem::size_of_val(&mask), mask.as_mut_ptr()) })?;
+        Ok(mask)
+    }
+
+    fn format_i32(value: i32, buffer: &mut [u8; 12]) -> &[u8] {
+        let mut start = buffer.len();
+        let mut remaining = value.unsigned_abs();
+        loop {
+            start -= 1;
+            buffer[start] = b'0' + (remaining % 10) as u8;
+            remaining /= 10;
+            if remaining == 0 {
+                break;
+            }
+        }
+        if value < 0 {
+            start -= 1;
+            buffer[start] = b'-';
+        }
+        &buffer[start..]
+    }
+
+    fn check(result: i32) -> io::Result<()> {
+        if result < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+// `Scheduling::new` refuses the settings, a processus keeps the inherited values
+#[cfg(not(target_os = "linux"))]
+impl Scheduling {
+    pub fn apply_self(&self) -> io::Result<()> {
+        Ok(())
+    }
+
+    pub fn apply(&self, _pid: u32, _old: &Scheduling) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+impl fmt::Display for Scheduling {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut settings = Vec::new();
+        if let Some(nice) = self.nice {
+            settings.push(format!("nice {nice}"));
+        }
+        if let Some((class, level)) = self.ionice {
+            settings.push(format!("ionice {class:?} {level}"));
+        }
+        if !self.cpu_affinity.is_empty() {
+            settings.push(format!("cpu_affinity {:?}", self.cpu_affinity));
+        }
+        if let Some(oom_score_adj) = self.oom_score_adj {
+            settings.push(format!("oom_score_adj {oom_score_adj}"));
+        }
+        if settings.is_empty() {
+            write!(f, "inherited")
+        } else {
+            write!(f, "{}", settings.join(", "))
+        }
+    }
+}
@@ -0,0 +1,5 @@
+#!/bin/bash
+
+for arg in "$@"; do
+    echo "[$arg]"
+done
@@ -0,0 +1,4 @@
+#!/bin/bash
+
+echo "$@"
+echo "$HOST $HOME_DIR $LOG_LEVEL"
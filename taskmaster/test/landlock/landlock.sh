@@ -0,0 +1,5 @@
+#!/bin/bash
+
+echo "allowed" > ./allowed.out
+echo "denied" > /tmp/landlock_denied.out
+cat /etc/hostname
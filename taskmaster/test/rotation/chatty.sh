@@ -0,0 +1,5 @@
+#!/bin/bash
+
+for i in $(seq 1 100); do
+    echo "line $i"
+done
@@ -0,0 +1,5 @@
+#!/bin/bash
+
+while read -r line; do
+    echo "received: $line"
+done
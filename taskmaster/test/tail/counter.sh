@@ -0,0 +1,9 @@
+#!/bin/bash
+
+i=0
+while true; do
+    i=$((i + 1))
+    echo "tick $i"
+    echo "error $i" >&2
+    sleep 0.3
+done